@@ -2,24 +2,72 @@
 #![forbid(unsafe_code)]
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 /// Prelude re-exporting the most commonly used items.
 pub mod prelude {
+  #[cfg(feature = "alloc")]
+  pub use super::OwnedTinySetQueue;
+  #[cfg(feature = "serde")]
+  pub use super::QueueState;
   #[cfg(feature = "pow2")]
   pub use super::TinySetQueuePow2;
   pub use super::{
-    MembershipMode, ProcessingOrder, PushResult, SetBacking, TinySetQueue,
+    CaptureError, CapturedState, EnsureResult, KeyedTinySetQueue,
+    MembershipMode, ProcessingOrder, PushError, PushOutcome, PushResult,
+    QueueBuilder, QueueConfig, QueueParts, QueueView, SetBacking, SetBitsIter,
+    StateError, TinySetArrayQueue, TinySetQueue, WideKey,
   };
 }
 
 mod private {
-  pub trait Sealed {}
+  pub trait Sealed {
+    /// Returns the membership bits for indices `64 * chunk..64 * chunk + 64`,
+    /// packed into a word. Indices beyond the backing's capacity read as clear.
+    fn chunk64(&self, chunk: usize) -> u64;
+  }
+
+  pub trait SealedView {}
+}
+
+/// Number of 64-entry chunks needed to cover `capacity` membership entries.
+#[inline]
+fn chunk_count(capacity: usize) -> usize {
+  (capacity >> 6) + usize::from(capacity & 63 != 0)
+}
+
+/// Address identifying a membership backing, or `0` for zero-sized backings.
+#[cfg(all(feature = "alias_check", debug_assertions))]
+fn backing_addr<S: SetBacking + ?Sized>(backing: &S) -> usize {
+  if core::mem::size_of_val(backing) == 0 {
+    0
+  } else {
+    backing as *const S as *const () as usize
+  }
+}
+
+/// Calls `f` with every index present in `backing`, in ascending order.
+fn for_each_set_bit<S, F>(backing: &S, mut f: F)
+where
+  S: SetBacking + ?Sized,
+  F: FnMut(usize),
+{
+  for chunk in 0..chunk_count(backing.capacity()) {
+    let mut bits = backing.chunk64(chunk);
+    while bits != 0 {
+      f((chunk << 6) + bits.trailing_zeros() as usize);
+      bits &= bits - 1;
+    }
+  }
 }
 
 /// Behavior required from membership backings.
 ///
 /// This trait is sealed; it can only be implemented by types provided by this
-/// crate (currently `[bool]` and `[u64]`). Users opt into different behaviors
-/// by passing these different slice types to [`TinySetQueue::new`].
+/// crate (currently `[bool]`, the `[u8]`/`[u32]`/`[u64]`/`[u128]` bitsets
+/// and, with the `atomic` feature, `[AtomicU64]`). Users opt into different
+/// behaviors by passing these different slice types to [`TinySetQueue::new`].
 pub trait SetBacking: private::Sealed {
   /// Number of representable entries in the membership domain.
   fn capacity(&self) -> usize;
@@ -31,9 +79,98 @@ pub trait SetBacking: private::Sealed {
   fn remove(&mut self, index: usize);
   /// Clears all membership information.
   fn clear_all(&mut self);
+  /// Clears membership for every index below `end`.
+  ///
+  /// Word-based backings clear whole words, so indices just past `end` in the
+  /// same word may also be cleared. `end` is clamped to the capacity.
+  fn clear_up_to(&mut self, end: usize);
+  /// Clears membership for exactly the indices in `start..end`.
+  ///
+  /// Unlike [`clear_up_to`](Self::clear_up_to), indices outside the range are
+  /// never touched, so a backing can be reused across sub-windows of its
+  /// domain. `end` is clamped to the capacity. The provided implementation
+  /// removes one index at a time; `[bool]` fills the range and `[u64]` zeroes
+  /// whole words, masking the partial words at either end.
+  fn clear_range(&mut self, start: usize, end: usize) {
+    for index in start..end.min(self.capacity()) {
+      self.remove(index);
+    }
+  }
+  /// Returns the number of indices present.
+  ///
+  /// The provided implementation probes every index; the built-in backings
+  /// override it with a word-wise popcount or a direct count.
+  fn count(&self) -> usize {
+    (0..self.capacity())
+      .filter(|&index| self.contains(index))
+      .count()
+  }
+  /// Returns an iterator over every index present, in ascending order.
+  ///
+  /// The backing is read 64 indices at a time and set bits are located with
+  /// `trailing_zeros`, so clear words are skipped cheaply.
+  fn iter_set_bits(&self) -> SetBitsIter<'_, Self> {
+    SetBitsIter {
+      backing: self,
+      chunk: 0,
+      bits: 0,
+      chunks: chunk_count(self.capacity()),
+    }
+  }
+}
+
+/// Iterator over the indices present in a membership backing, in ascending
+/// order.
+///
+/// Created by [`SetBacking::iter_set_bits`] and
+/// [`TinySetQueue::visited_indices`].
+pub struct SetBitsIter<'a, S: ?Sized> {
+  backing: &'a S,
+  /// Next chunk to load once `bits` is exhausted.
+  chunk: usize,
+  /// Unyielded bits of chunk `chunk - 1`.
+  bits: u64,
+  /// Number of chunks covering the backing.
+  chunks: usize,
+}
+
+impl<'a, S> Iterator for SetBitsIter<'a, S>
+where
+  S: SetBacking + ?Sized,
+{
+  type Item = usize;
+
+  fn next(&mut self) -> Option<usize> {
+    while self.bits == 0 {
+      if self.chunk >= self.chunks {
+        return None;
+      }
+      self.bits = self.backing.chunk64(self.chunk);
+      self.chunk += 1;
+    }
+    let index = ((self.chunk - 1) << 6) + self.bits.trailing_zeros() as usize;
+    self.bits &= self.bits - 1;
+    Some(index)
+  }
 }
 
-impl private::Sealed for [bool] {}
+impl<'a, S> core::iter::FusedIterator for SetBitsIter<'a, S> where
+  S: SetBacking + ?Sized
+{
+}
+
+impl private::Sealed for [bool] {
+  fn chunk64(&self, chunk: usize) -> u64 {
+    let start = (chunk << 6).min(self.len());
+    let end = (start + 64).min(self.len());
+    self[start..end]
+      .iter()
+      .enumerate()
+      .fold(0, |bits, (bit, &present)| {
+        bits | (u64::from(present) << bit)
+      })
+  }
+}
 
 impl SetBacking for [bool] {
   #[inline(always)]
@@ -59,9 +196,30 @@ impl SetBacking for [bool] {
   fn clear_all(&mut self) {
     self.fill(false);
   }
+
+  fn clear_up_to(&mut self, end: usize) {
+    let end = end.min(self.len());
+    self[..end].fill(false);
+  }
+
+  fn clear_range(&mut self, start: usize, end: usize) {
+    let end = end.min(self.len());
+    if start < end {
+      self[start..end].fill(false);
+    }
+  }
+
+  fn count(&self) -> usize {
+    self.iter().filter(|present| **present).count()
+  }
 }
 
-impl private::Sealed for [u64] {}
+impl private::Sealed for [u64] {
+  #[inline(always)]
+  fn chunk64(&self, chunk: usize) -> u64 {
+    self.get(chunk).copied().unwrap_or(0)
+  }
+}
 
 impl SetBacking for [u64] {
   #[inline(always)]
@@ -93,9 +251,39 @@ impl SetBacking for [u64] {
   fn clear_all(&mut self) {
     self.fill(0);
   }
+
+  fn clear_up_to(&mut self, end: usize) {
+    let words = ((end >> 6) + usize::from(end & 63 != 0)).min(self.len());
+    self[..words].fill(0);
+  }
+
+  fn clear_range(&mut self, start: usize, end: usize) {
+    let end = end.min(self.len() << 6);
+    if start >= end {
+      return;
+    }
+    let (first, last) = (start >> 6, (end - 1) >> 6);
+    let low = u64::MAX << (start & 63);
+    let high = u64::MAX >> (63 - ((end - 1) & 63));
+    if first == last {
+      self[first] &= !(low & high);
+      return;
+    }
+    self[first] &= !low;
+    self[first + 1..last].fill(0);
+    self[last] &= !high;
+  }
+
+  fn count(&self) -> usize {
+    self.iter().map(|word| word.count_ones() as usize).sum()
+  }
 }
 
-impl<const N: usize> private::Sealed for [bool; N] {}
+impl<const N: usize> private::Sealed for [bool; N] {
+  fn chunk64(&self, chunk: usize) -> u64 {
+    self[..].chunk64(chunk)
+  }
+}
 
 impl<const N: usize> SetBacking for [bool; N] {
   #[inline(always)]
@@ -121,9 +309,30 @@ impl<const N: usize> SetBacking for [bool; N] {
   fn clear_all(&mut self) {
     self.fill(false);
   }
+
+  fn clear_up_to(&mut self, end: usize) {
+    let end = end.min(self.len());
+    self[..end].fill(false);
+  }
+
+  fn clear_range(&mut self, start: usize, end: usize) {
+    let end = end.min(self.len());
+    if start < end {
+      self[start..end].fill(false);
+    }
+  }
+
+  fn count(&self) -> usize {
+    self.iter().filter(|present| **present).count()
+  }
 }
 
-impl<const N: usize> private::Sealed for [u64; N] {}
+impl<const N: usize> private::Sealed for [u64; N] {
+  #[inline(always)]
+  fn chunk64(&self, chunk: usize) -> u64 {
+    self[..].chunk64(chunk)
+  }
+}
 
 impl<const N: usize> SetBacking for [u64; N] {
   #[inline(always)]
@@ -155,340 +364,8314 @@ impl<const N: usize> SetBacking for [u64; N] {
   fn clear_all(&mut self) {
     self.fill(0);
   }
-}
 
-/// Result of attempting to enqueue a value.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum PushResult {
-  /// The value was inserted into the queue.
-  Inserted,
-  /// The value was already present and was not enqueued again.
-  AlreadyPresent,
-}
+  fn clear_up_to(&mut self, end: usize) {
+    let words = ((end >> 6) + usize::from(end & 63 != 0)).min(self.len());
+    self[..words].fill(0);
+  }
 
-/// Controls how membership is tracked when popping values.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum MembershipMode {
-  /// Membership is cleared upon popping, allowing the value to be enqueued again.
-  InQueue,
-  /// Membership persists after popping, preventing re-enqueueing.
-  Visited,
-}
+  fn clear_range(&mut self, start: usize, end: usize) {
+    self[..].clear_range(start, end);
+  }
 
-/// Controls whether values are processed in FIFO or LIFO order.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum ProcessingOrder {
-  /// First-in, first-out processing (queue semantics).
-  Fifo,
-  /// Last-in, first-out processing (stack semantics).
-  Lifo,
+  fn count(&self) -> usize {
+    self.iter().map(|word| word.count_ones() as usize).sum()
+  }
 }
 
-/// A fixed-capacity, allocation-free queue with direct-mapped membership tracking.
-///
-/// Values are converted to indices via [`Into<usize>`], so the queue works best when
-/// keys are dense integers in the range `0..N`. Sparse identifiers (e.g. `{5, 1_000_000}`)
-/// require a membership backing large enough to cover the full domain.
-///
-/// # Sizing
-///
-/// This queue never allocates and **never resizes** at runtime. If an index
-/// exceeds the membership capacity, `push` returns an error.
-pub struct TinySetQueue<'a, T, S>
-where
-  S: SetBacking + ?Sized,
-{
-  buf: &'a mut [T],
-  in_queue: &'a mut S,
-  mode: MembershipMode,
-  order: ProcessingOrder,
-  head: usize,
-  tail: usize,
-  len: usize,
+impl private::Sealed for [u32] {
+  #[inline(always)]
+  fn chunk64(&self, chunk: usize) -> u64 {
+    let low = self.get(chunk << 1).copied().unwrap_or(0);
+    let high = self.get((chunk << 1) + 1).copied().unwrap_or(0);
+    u64::from(low) | (u64::from(high) << 32)
+  }
 }
 
-impl<'a, T, S> TinySetQueue<'a, T, S>
-where
-  T: Copy + Into<usize>,
-  S: SetBacking + ?Sized,
-{
-  /// Constructs a queue backed by caller-provided storage.
-  ///
-  /// * `buf` supplies the ring-buffer storage used for pending values.
-  /// * `in_queue` is the direct-mapped membership backing (e.g. `[bool]`, `[u64]`).
-  /// * `mode` determines whether membership clears on `pop`.
-  /// * `order` selects FIFO or LIFO processing of queued values.
-  ///
-  /// `in_queue.capacity()` must exceed any index produced by `value.into()`. When the
-  /// `clear_on_new` feature (enabled by default) is active, the backing is cleared to
-  /// prevent stale membership flags.
-  pub fn new(
-    buf: &'a mut [T],
-    in_queue: &'a mut S,
-    mode: MembershipMode,
-    order: ProcessingOrder,
-  ) -> Self {
-    #[cfg(feature = "clear_on_new")]
-    in_queue.clear_all();
-    TinySetQueue {
-      buf,
-      in_queue,
-      mode,
-      order,
-      head: 0,
-      tail: 0,
-      len: 0,
-    }
+impl SetBacking for [u32] {
+  #[inline(always)]
+  fn capacity(&self) -> usize {
+    self.len() << 5
   }
 
-  /// Clears the queue without freeing any backing storage.
-  ///
-  /// All membership flags are reset and the queue becomes empty.
-  pub fn clear(&mut self) {
-    self.in_queue.clear_all();
-    self.head = 0;
-    self.tail = 0;
-    self.len = 0;
+  #[inline(always)]
+  fn contains(&self, index: usize) -> bool {
+    let word = index >> 5;
+    let bit = index & 31;
+    (self[word] & (1u32 << bit)) != 0
   }
 
-  /// Returns the maximum number of pending items the queue can hold.
-  #[inline]
-  pub fn capacity(&self) -> usize {
-    self.buf.len()
+  #[inline(always)]
+  fn insert(&mut self, index: usize) {
+    let word = index >> 5;
+    let bit = index & 31;
+    self[word] |= 1u32 << bit;
   }
 
-  /// Returns the number of items currently enqueued.
-  #[inline]
-  pub fn len(&self) -> usize {
-    self.len
+  #[inline(always)]
+  fn remove(&mut self, index: usize) {
+    let word = index >> 5;
+    let bit = index & 31;
+    self[word] &= !(1u32 << bit);
   }
 
-  /// Returns `true` when the queue is empty.
-  #[inline]
-  pub fn is_empty(&self) -> bool {
-    self.len == 0
+  fn clear_all(&mut self) {
+    self.fill(0);
   }
 
-  /// Returns `true` when the queue is at full capacity.
-  #[inline]
-  pub fn is_full(&self) -> bool {
-    self.len == self.buf.len()
+  fn clear_up_to(&mut self, end: usize) {
+    let words = ((end >> 5) + usize::from(end & 31 != 0)).min(self.len());
+    self[..words].fill(0);
   }
 
-  /// Pushes a value into the queue unless it is already present.
-  ///
-  /// # Errors
-  ///
-  /// Returns `Err(value)` if the queue is full or if `value.into()` exceeds the
-  /// bounds of the membership backing.
-  pub fn push(&mut self, value: T) -> Result<PushResult, T> {
-    let idx: usize = value.into();
-
-    if idx >= self.in_queue.capacity() {
-      return Err(value);
-    }
-
-    if self.in_queue.contains(idx) {
-      return Ok(PushResult::AlreadyPresent);
-    }
-
-    if self.is_full() {
-      return Err(value);
-    }
+  fn count(&self) -> usize {
+    self.iter().map(|word| word.count_ones() as usize).sum()
+  }
+}
 
-    self.buf[self.tail] = value;
-    self.in_queue.insert(idx);
+impl<const N: usize> private::Sealed for [u32; N] {
+  #[inline(always)]
+  fn chunk64(&self, chunk: usize) -> u64 {
+    self[..].chunk64(chunk)
+  }
+}
 
-    self.tail = (self.tail + 1) % self.buf.len();
-    self.len += 1;
+impl<const N: usize> SetBacking for [u32; N] {
+  #[inline(always)]
+  fn capacity(&self) -> usize {
+    N << 5
+  }
 
-    Ok(PushResult::Inserted)
+  #[inline(always)]
+  fn contains(&self, index: usize) -> bool {
+    let word = index >> 5;
+    let bit = index & 31;
+    (self[word] & (1u32 << bit)) != 0
   }
 
-  /// Pops the next value according to the configured processing order, if any.
-  ///
-  /// Membership is cleared in [`MembershipMode::InQueue`] and retained in
-  /// [`MembershipMode::Visited`].
-  pub fn pop(&mut self) -> Option<T> {
-    if self.is_empty() {
-      return None;
-    }
+  #[inline(always)]
+  fn insert(&mut self, index: usize) {
+    let word = index >> 5;
+    let bit = index & 31;
+    self[word] |= 1u32 << bit;
+  }
 
-    let index = match self.order {
-      ProcessingOrder::Fifo => {
-        let idx = self.head;
-        self.head = (self.head + 1) % self.buf.len();
-        idx
-      }
-      ProcessingOrder::Lifo => {
-        debug_assert!(self.buf.len() > 0);
-        let idx = if self.tail == 0 {
-          self.buf.len() - 1
-        } else {
-          self.tail - 1
-        };
-        self.tail = idx;
-        idx
-      }
-    };
+  #[inline(always)]
+  fn remove(&mut self, index: usize) {
+    let word = index >> 5;
+    let bit = index & 31;
+    self[word] &= !(1u32 << bit);
+  }
 
-    let value = self.buf[index];
-    let idx: usize = value.into();
+  fn clear_all(&mut self) {
+    self.fill(0);
+  }
 
-    if matches!(self.mode, MembershipMode::InQueue) {
-      self.in_queue.remove(idx);
-    }
+  fn clear_up_to(&mut self, end: usize) {
+    let words = ((end >> 5) + usize::from(end & 31 != 0)).min(self.len());
+    self[..words].fill(0);
+  }
 
-    self.len -= 1;
+  fn count(&self) -> usize {
+    self.iter().map(|word| word.count_ones() as usize).sum()
+  }
+}
 
-    Some(value)
+impl private::Sealed for [u8] {
+  #[inline(always)]
+  fn chunk64(&self, chunk: usize) -> u64 {
+    let start = (chunk << 3).min(self.len());
+    let end = (start + 8).min(self.len());
+    self[start..end]
+      .iter()
+      .rev()
+      .fold(0, |word, &byte| (word << 8) | u64::from(byte))
   }
 }
 
-/// A power-of-two capacity variant that uses bit masking for wrap-around.
-///
-/// As with [`TinySetQueue`], membership is direct-mapped: the membership backing must be
-/// large enough to cover the entire domain addressable by `T::into()`.
-#[cfg(feature = "pow2")]
-pub struct TinySetQueuePow2<'a, T, S>
-where
-  S: SetBacking + ?Sized,
-{
-  buf: &'a mut [T],
-  in_queue: &'a mut S,
-  mode: MembershipMode,
-  order: ProcessingOrder,
-  mask: usize,
-  head: usize,
-  tail: usize,
+impl SetBacking for [u8] {
+  #[inline(always)]
+  fn capacity(&self) -> usize {
+    self.len() << 3
+  }
+
+  #[inline(always)]
+  fn contains(&self, index: usize) -> bool {
+    let byte = index >> 3;
+    let bit = index & 7;
+    (self[byte] & (1u8 << bit)) != 0
+  }
+
+  #[inline(always)]
+  fn insert(&mut self, index: usize) {
+    let byte = index >> 3;
+    let bit = index & 7;
+    self[byte] |= 1u8 << bit;
+  }
+
+  #[inline(always)]
+  fn remove(&mut self, index: usize) {
+    let byte = index >> 3;
+    let bit = index & 7;
+    self[byte] &= !(1u8 << bit);
+  }
+
+  fn clear_all(&mut self) {
+    self.fill(0);
+  }
+
+  fn clear_up_to(&mut self, end: usize) {
+    let bytes = ((end >> 3) + usize::from(end & 7 != 0)).min(self.len());
+    self[..bytes].fill(0);
+  }
+
+  fn count(&self) -> usize {
+    self.iter().map(|byte| byte.count_ones() as usize).sum()
+  }
+}
+
+impl<const N: usize> private::Sealed for [u8; N] {
+  #[inline(always)]
+  fn chunk64(&self, chunk: usize) -> u64 {
+    self[..].chunk64(chunk)
+  }
+}
+
+impl<const N: usize> SetBacking for [u8; N] {
+  #[inline(always)]
+  fn capacity(&self) -> usize {
+    N << 3
+  }
+
+  #[inline(always)]
+  fn contains(&self, index: usize) -> bool {
+    let byte = index >> 3;
+    let bit = index & 7;
+    (self[byte] & (1u8 << bit)) != 0
+  }
+
+  #[inline(always)]
+  fn insert(&mut self, index: usize) {
+    let byte = index >> 3;
+    let bit = index & 7;
+    self[byte] |= 1u8 << bit;
+  }
+
+  #[inline(always)]
+  fn remove(&mut self, index: usize) {
+    let byte = index >> 3;
+    let bit = index & 7;
+    self[byte] &= !(1u8 << bit);
+  }
+
+  fn clear_all(&mut self) {
+    self.fill(0);
+  }
+
+  fn clear_up_to(&mut self, end: usize) {
+    let bytes = ((end >> 3) + usize::from(end & 7 != 0)).min(self.len());
+    self[..bytes].fill(0);
+  }
+
+  fn count(&self) -> usize {
+    self.iter().map(|byte| byte.count_ones() as usize).sum()
+  }
+}
+
+impl private::Sealed for [u128] {
+  #[inline(always)]
+  fn chunk64(&self, chunk: usize) -> u64 {
+    let word = self.get(chunk >> 1).copied().unwrap_or(0);
+    (word >> ((chunk & 1) << 6)) as u64
+  }
+}
+
+impl SetBacking for [u128] {
+  #[inline(always)]
+  fn capacity(&self) -> usize {
+    self.len() << 7
+  }
+
+  #[inline(always)]
+  fn contains(&self, index: usize) -> bool {
+    let word = index >> 7;
+    let bit = index & 127;
+    (self[word] & (1u128 << bit)) != 0
+  }
+
+  #[inline(always)]
+  fn insert(&mut self, index: usize) {
+    let word = index >> 7;
+    let bit = index & 127;
+    self[word] |= 1u128 << bit;
+  }
+
+  #[inline(always)]
+  fn remove(&mut self, index: usize) {
+    let word = index >> 7;
+    let bit = index & 127;
+    self[word] &= !(1u128 << bit);
+  }
+
+  fn clear_all(&mut self) {
+    self.fill(0);
+  }
+
+  fn clear_up_to(&mut self, end: usize) {
+    let words = ((end >> 7) + usize::from(end & 127 != 0)).min(self.len());
+    self[..words].fill(0);
+  }
+
+  fn count(&self) -> usize {
+    self.iter().map(|word| word.count_ones() as usize).sum()
+  }
+}
+
+impl<const N: usize> private::Sealed for [u128; N] {
+  #[inline(always)]
+  fn chunk64(&self, chunk: usize) -> u64 {
+    self[..].chunk64(chunk)
+  }
+}
+
+impl<const N: usize> SetBacking for [u128; N] {
+  #[inline(always)]
+  fn capacity(&self) -> usize {
+    N << 7
+  }
+
+  #[inline(always)]
+  fn contains(&self, index: usize) -> bool {
+    let word = index >> 7;
+    let bit = index & 127;
+    (self[word] & (1u128 << bit)) != 0
+  }
+
+  #[inline(always)]
+  fn insert(&mut self, index: usize) {
+    let word = index >> 7;
+    let bit = index & 127;
+    self[word] |= 1u128 << bit;
+  }
+
+  #[inline(always)]
+  fn remove(&mut self, index: usize) {
+    let word = index >> 7;
+    let bit = index & 127;
+    self[word] &= !(1u128 << bit);
+  }
+
+  fn clear_all(&mut self) {
+    self.fill(0);
+  }
+
+  fn clear_up_to(&mut self, end: usize) {
+    let words = ((end >> 7) + usize::from(end & 127 != 0)).min(self.len());
+    self[..words].fill(0);
+  }
+
+  fn count(&self) -> usize {
+    self.iter().map(|word| word.count_ones() as usize).sum()
+  }
+}
+
+/// Atomic bitset backings, enabled by the `atomic` feature.
+///
+/// Membership lives in `AtomicU64` words: `contains` is a `Relaxed` load and
+/// `insert`/`remove` are `Relaxed` `fetch_or`/`fetch_and`, so other threads
+/// can query membership while a queue is updating it. Only those reads are
+/// shareable; the queue itself stays single-threaded. A queue over
+/// `[AtomicU64]` borrows the words mutably, which rules out concurrent
+/// readers, so to share them hand the queue a `&[AtomicU64]` instead:
+///
+/// ```
+/// use core::sync::atomic::AtomicU64;
+/// use tinysetqueue::{MembershipMode, ProcessingOrder, SetBacking, TinySetQueue};
+///
+/// let words = [AtomicU64::new(0), AtomicU64::new(0)];
+/// let mut writer: &[AtomicU64] = &words;
+/// let mut buf = [0u8; 4];
+/// let mut queue = TinySetQueue::new(
+///   &mut buf,
+///   &mut writer,
+///   MembershipMode::Visited,
+///   ProcessingOrder::Fifo,
+/// );
+/// queue.push(70).unwrap();
+/// // `words` is still readable, e.g. from another thread.
+/// assert!(SetBacking::contains(&words[..], 70));
+/// ```
+#[cfg(all(feature = "atomic", target_has_atomic = "64"))]
+mod atomic {
+  use super::{private, SetBacking};
+  use core::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+  impl private::Sealed for [AtomicU64] {
+    #[inline(always)]
+    fn chunk64(&self, chunk: usize) -> u64 {
+      self.get(chunk).map_or(0, |word| word.load(Relaxed))
+    }
+  }
+
+  impl SetBacking for [AtomicU64] {
+    #[inline(always)]
+    fn capacity(&self) -> usize {
+      self.len() << 6
+    }
+
+    #[inline(always)]
+    fn contains(&self, index: usize) -> bool {
+      let word = index >> 6;
+      let bit = index & 63;
+      (self[word].load(Relaxed) & (1u64 << bit)) != 0
+    }
+
+    #[inline(always)]
+    fn insert(&mut self, index: usize) {
+      (&*self).insert(index);
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, index: usize) {
+      (&*self).remove(index);
+    }
+
+    fn clear_all(&mut self) {
+      (&*self).clear_all();
+    }
+
+    fn clear_up_to(&mut self, end: usize) {
+      (&*self).clear_up_to(end);
+    }
+
+    fn count(&self) -> usize {
+      self
+        .iter()
+        .map(|word| word.load(Relaxed).count_ones() as usize)
+        .sum()
+    }
+  }
+
+  impl private::Sealed for &[AtomicU64] {
+    #[inline(always)]
+    fn chunk64(&self, chunk: usize) -> u64 {
+      (**self).chunk64(chunk)
+    }
+  }
+
+  /// Shared handle to atomic words; updates go through the shared reference,
+  /// so the words stay readable elsewhere while a queue owns the handle.
+  impl SetBacking for &[AtomicU64] {
+    #[inline(always)]
+    fn capacity(&self) -> usize {
+      (**self).capacity()
+    }
+
+    #[inline(always)]
+    fn contains(&self, index: usize) -> bool {
+      SetBacking::contains(*self, index)
+    }
+
+    #[inline(always)]
+    fn insert(&mut self, index: usize) {
+      let word = index >> 6;
+      let bit = index & 63;
+      self[word].fetch_or(1u64 << bit, Relaxed);
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, index: usize) {
+      let word = index >> 6;
+      let bit = index & 63;
+      self[word].fetch_and(!(1u64 << bit), Relaxed);
+    }
+
+    fn clear_all(&mut self) {
+      for word in self.iter() {
+        word.store(0, Relaxed);
+      }
+    }
+
+    fn clear_up_to(&mut self, end: usize) {
+      let words = ((end >> 6) + usize::from(end & 63 != 0)).min(self.len());
+      for word in self[..words].iter() {
+        word.store(0, Relaxed);
+      }
+    }
+
+    fn count(&self) -> usize {
+      (**self).count()
+    }
+  }
+}
+
+/// Operation scripts for fuzzing queue usage with `cargo-fuzz`.
+///
+/// Enabled by the `arbitrary` feature. Generate a `Vec<Op<T>>` from fuzzer
+/// input and replay it against a queue with
+/// [`Op::apply`](crate::fuzz::Op::apply).
+#[cfg(feature = "arbitrary")]
+pub mod fuzz {
+  use super::{SetBacking, TinySetQueue};
+  use arbitrary::{Arbitrary, Unstructured};
+
+  /// A single scripted queue operation.
+  #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+  pub enum Op<T> {
+    /// Push the value, ignoring the result.
+    Push(T),
+    /// Pop the next value.
+    Pop,
+    /// Clear the queue and its membership.
+    Clear,
+  }
+
+  impl<T> Op<T>
+  where
+    T: Copy + Into<usize>,
+  {
+    /// Applies the operation to `queue`, returning the popped value for
+    /// [`Op::Pop`].
+    pub fn apply<S>(self, queue: &mut TinySetQueue<'_, T, S>) -> Option<T>
+    where
+      S: SetBacking + ?Sized,
+    {
+      match self {
+        Op::Push(value) => {
+          let _ = queue.push(value);
+          None
+        }
+        Op::Pop => queue.pop(),
+        Op::Clear => {
+          queue.clear();
+          None
+        }
+      }
+    }
+  }
+
+  impl<'a, T> Arbitrary<'a> for Op<T>
+  where
+    T: Arbitrary<'a>,
+  {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+      // Weight pushes so generated scripts actually fill the queue.
+      Ok(match u.int_in_range(0..=7u8)? {
+        0..=4 => Op::Push(T::arbitrary(u)?),
+        5 | 6 => Op::Pop,
+        _ => Op::Clear,
+      })
+    }
+  }
+}
+
+/// Number of recently popped indices retained by the `audit` feature.
+#[cfg(feature = "audit")]
+pub const AUDIT_CAPACITY: usize = 16;
+
+/// Fixed-size record of the indices most recently popped in `Visited` mode.
+#[cfg(feature = "audit")]
+struct AuditRing {
+  entries: [usize; AUDIT_CAPACITY],
   len: usize,
 }
 
-#[cfg(feature = "pow2")]
-impl<'a, T, S> TinySetQueuePow2<'a, T, S>
-where
-  T: Copy + Into<usize>,
-  S: SetBacking + ?Sized,
-{
-  /// Constructs a queue backed by power-of-two-sized storage.
-  ///
-  /// # Panics
-  ///
-  /// Panics if `buf.len()` is not a power of two.
-  pub fn new(
-    buf: &'a mut [T],
-    in_queue: &'a mut S,
-    mode: MembershipMode,
-    order: ProcessingOrder,
-  ) -> Self {
-    assert!(
-      buf.len().is_power_of_two(),
-      "buffer length must be a power of two"
+#[cfg(feature = "audit")]
+impl AuditRing {
+  const fn new() -> Self {
+    AuditRing {
+      entries: [0; AUDIT_CAPACITY],
+      len: 0,
+    }
+  }
+
+  fn record(&mut self, idx: usize) {
+    if self.len == AUDIT_CAPACITY {
+      self.entries.copy_within(1.., 0);
+      self.len -= 1;
+    }
+    self.entries[self.len] = idx;
+    self.len += 1;
+  }
+
+  fn forget_last(&mut self, idx: usize) {
+    if self.len > 0 && self.entries[self.len - 1] == idx {
+      self.len -= 1;
+    }
+  }
+
+  fn clear(&mut self) {
+    self.len = 0;
+  }
+
+  fn as_slice(&self) -> &[usize] {
+    &self.entries[..self.len]
+  }
+}
+
+#[cfg(all(feature = "leak_check", debug_assertions))]
+static LEAKED_DROPS: core::sync::atomic::AtomicUsize =
+  core::sync::atomic::AtomicUsize::new(0);
+
+/// Returns how many `InQueue` queues have been dropped with pending values.
+///
+/// Only tracked with the `leak_check` feature in debug builds; always `0`
+/// otherwise. The counter is global and monotonic, so tests should compare it
+/// before and after the code under test.
+pub fn leaked_queue_drops() -> usize {
+  #[cfg(all(feature = "leak_check", debug_assertions))]
+  {
+    LEAKED_DROPS.load(core::sync::atomic::Ordering::Relaxed)
+  }
+  #[cfg(not(all(feature = "leak_check", debug_assertions)))]
+  {
+    0
+  }
+}
+
+/// Drop-time check that an `InQueue` queue was drained.
+///
+/// The queue mirrors its pending count and mode into this guard whenever they
+/// change; the guard cannot observe its parent directly.
+#[cfg(all(feature = "leak_check", debug_assertions))]
+struct LeakCheck {
+  pending: usize,
+  in_queue_mode: bool,
+  enabled: bool,
+}
+
+#[cfg(all(feature = "leak_check", debug_assertions))]
+impl LeakCheck {
+  const fn new(mode: MembershipMode, enabled: bool) -> Self {
+    LeakCheck {
+      pending: 0,
+      in_queue_mode: matches!(mode, MembershipMode::InQueue),
+      enabled,
+    }
+  }
+}
+
+#[cfg(all(feature = "leak_check", debug_assertions))]
+impl Drop for LeakCheck {
+  fn drop(&mut self) {
+    if self.enabled && self.in_queue_mode && self.pending > 0 {
+      LEAKED_DROPS.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+      #[cfg(feature = "std")]
+      std::eprintln!(
+        "tinysetqueue: InQueue queue dropped with {} pending value(s)",
+        self.pending
+      );
+    }
+  }
+}
+
+/// Returns how many queues have been handed a membership backing that
+/// another live queue already held.
+///
+/// Only tracked with the `alias_check` feature in debug builds; always `0`
+/// otherwise. Detection is best effort: the table of live backings has a
+/// fixed number of slots, registrations beyond it go untracked, and a queue
+/// passed to [`core::mem::forget`] keeps its backing registered, so a later
+/// queue on the same storage is counted as a collision. The counter is global
+/// and monotonic, so tests should compare it before and after the code under
+/// test.
+pub fn aliased_backings() -> usize {
+  #[cfg(all(feature = "alias_check", debug_assertions))]
+  {
+    alias::COLLISIONS.load(core::sync::atomic::Ordering::Relaxed)
+  }
+  #[cfg(not(all(feature = "alias_check", debug_assertions)))]
+  {
+    0
+  }
+}
+
+#[cfg(all(feature = "alias_check", debug_assertions))]
+mod alias {
+  use core::sync::atomic::{AtomicUsize, Ordering};
+
+  const SLOTS: usize = 32;
+
+  #[allow(clippy::declare_interior_mutable_const)]
+  const FREE: AtomicUsize = AtomicUsize::new(0);
+
+  /// Addresses of membership backings owned by live queues; `0` marks a free slot.
+  static LIVE: [AtomicUsize; SLOTS] = [FREE; SLOTS];
+
+  /// Number of registrations that found their address already live.
+  pub(crate) static COLLISIONS: AtomicUsize = AtomicUsize::new(0);
+
+  /// Registration of a membership backing with a live queue, released on drop.
+  pub(crate) struct BackingToken {
+    slot: Option<usize>,
+  }
+
+  impl BackingToken {
+    /// Registers `addr`, counting a collision if another live queue already
+    /// holds it.
+    ///
+    /// Each address probes the table from its own starting slot, and every
+    /// probe is a single compare-exchange that either claims a free slot or
+    /// reports what occupies it, so the duplicate check and the claim cannot
+    /// interleave with another registration of the same address. An address
+    /// of `0` (used for zero-sized backings) is never tracked, and neither is
+    /// any address once the table is full.
+    pub(crate) fn register(addr: usize) -> Self {
+      if addr == 0 {
+        return BackingToken { slot: None };
+      }
+      let start = (addr >> 3) % SLOTS;
+      for step in 0..SLOTS {
+        let slot = (start + step) % SLOTS;
+        match LIVE[slot].compare_exchange(
+          0,
+          addr,
+          Ordering::AcqRel,
+          Ordering::Acquire,
+        ) {
+          Ok(_) => return BackingToken { slot: Some(slot) },
+          Err(live) if live == addr => {
+            COLLISIONS.fetch_add(1, Ordering::Relaxed);
+            #[cfg(feature = "std")]
+            std::eprintln!(
+              "tinysetqueue: membership backing is already in use by another \
+               live queue"
+            );
+            return BackingToken { slot: None };
+          }
+          Err(_) => {}
+        }
+      }
+      BackingToken { slot: None }
+    }
+  }
+
+  impl Drop for BackingToken {
+    fn drop(&mut self) {
+      if let Some(slot) = self.slot {
+        LIVE[slot].store(0, Ordering::Release);
+      }
+    }
+  }
+}
+
+/// Read-only view over a queue's pending values and membership.
+///
+/// Implemented by [`TinySetQueue`] and, with the `pow2` feature,
+/// `TinySetQueuePow2`. The trait is sealed. Accept `&impl QueueView<T>` in code
+/// that should inspect a worklist without being able to consume it.
+pub trait QueueView<T>: private::SealedView {
+  /// Returns the number of items currently enqueued.
+  fn len(&self) -> usize;
+  /// Returns `true` when the queue is empty.
+  fn is_empty(&self) -> bool;
+  /// Returns `true` when the queue is at full capacity.
+  fn is_full(&self) -> bool;
+  /// Returns the maximum number of pending items the queue can hold.
+  fn capacity(&self) -> usize;
+  /// Returns the value the next `pop` would yield, without removing it.
+  fn peek(&self) -> Option<&T>;
+  /// Returns `true` when `value` is marked in the membership backing.
+  fn contains(&self, value: T) -> bool;
+  /// Returns an iterator over the pending values in processing order.
+  fn iter(&self) -> Iter<'_, T>;
+}
+
+/// Result of attempting to enqueue a value.
+///
+/// New variants may be added for new push methods; plain `push` only ever
+/// returns `Inserted` or `AlreadyPresent`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PushResult {
+  /// The value was inserted into the queue.
+  Inserted,
+  /// The value was already present and was not enqueued again.
+  AlreadyPresent,
+  /// The value was already pending and its queued entry was updated in place
+  /// or moved. Returned by [`TinySetQueue::push_or_promote`].
+  Refreshed,
+  /// The value's index is in the caller's exclusion set and was not enqueued.
+  /// Returned by [`TinySetQueue::push_unless_excluded`].
+  Excluded,
+  /// The value was already pending and its queued copy was overwritten in
+  /// place, keeping its position. Returned by
+  /// [`TinySetQueue::push_or_replace`].
+  Replaced,
+}
+
+/// Outcome of [`TinySetQueue::ensure_queued`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EnsureResult {
+  /// The value was not marked and has been enqueued.
+  NewlyQueued,
+  /// A value with the same index is pending.
+  AlreadyQueued,
+  /// The index is marked but no longer pending: it was already popped in
+  /// [`MembershipMode::Visited`].
+  AlreadyVisited,
+}
+
+/// Every outcome of [`TinySetQueue::push_if_in_range`] as a single value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PushOutcome {
+  /// The index was in range and the queue had room or already held it.
+  Pushed(PushResult),
+  /// `value.into()` was not below the membership backing's capacity.
+  OutOfRange,
+  /// The value was new but the ring buffer had no free slot.
+  Full,
+}
+
+/// Reason a value could not be enqueued, carrying the rejected value back.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PushError<T> {
+  /// The ring buffer had no free slot.
+  Full(T),
+  /// `value.into()` was not below the membership backing's capacity.
+  OutOfRange(T),
+}
+
+impl<T> PushError<T> {
+  /// Returns the rejected value.
+  pub fn into_inner(self) -> T {
+    match self {
+      PushError::Full(value) | PushError::OutOfRange(value) => value,
+    }
+  }
+}
+
+impl<T> core::fmt::Display for PushError<T> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    match self {
+      PushError::Full(_) => f.write_str("queue is full"),
+      PushError::OutOfRange(_) => {
+        f.write_str("index exceeds the membership backing capacity")
+      }
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl<T: core::fmt::Debug> std::error::Error for PushError<T> {}
+
+/// Wraps a key that converts to `usize` only fallibly, such as `u64` on a
+/// 32-bit target or a signed integer, so it can be used as a queue value.
+///
+/// A key whose `try_into()` fails maps to `usize::MAX`, which no membership
+/// backing can represent, so pushing it is rejected with
+/// [`PushError::OutOfRange`] instead of being truncated. Keys that do convert
+/// take the same path as any other `Into<usize>` value.
+///
+/// ```
+/// use tinysetqueue::{MembershipMode, ProcessingOrder, PushError, TinySetQueue, WideKey};
+///
+/// let mut buf = [WideKey(0i64); 4];
+/// let mut membership = [false; 8];
+/// let mut queue = TinySetQueue::new(
+///   &mut buf,
+///   &mut membership,
+///   MembershipMode::InQueue,
+///   ProcessingOrder::Fifo,
+/// );
+/// assert!(queue.push(WideKey(3)).is_ok());
+/// assert_eq!(queue.push(WideKey(-1)), Err(PushError::OutOfRange(WideKey(-1))));
+/// assert_eq!(queue.pop(), Some(WideKey(3)));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct WideKey<K>(pub K);
+
+impl<K: TryInto<usize>> From<WideKey<K>> for usize {
+  #[inline]
+  fn from(key: WideKey<K>) -> usize {
+    key.0.try_into().unwrap_or(usize::MAX)
+  }
+}
+
+/// First inconsistency found when rebuilding a queue from stored state.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StateError {
+  /// `head`, `tail` and `len` do not describe a valid window of the buffer.
+  Indices(&'static str),
+  /// The pending value at `offset` from `head` maps to an index beyond the
+  /// membership backing.
+  OutOfRange {
+    /// Position of the value in insertion order.
+    offset: usize,
+    /// The value's membership index.
+    index: usize,
+  },
+  /// The pending value at `offset` from `head` is not marked in the
+  /// membership backing.
+  Unmarked {
+    /// Position of the value in insertion order.
+    offset: usize,
+    /// The value's membership index.
+    index: usize,
+  },
+  /// The pending value at `offset` from `head` has the same index as an
+  /// older pending value.
+  Duplicate {
+    /// Position of the repeated value in insertion order.
+    offset: usize,
+    /// The shared membership index.
+    index: usize,
+  },
+  /// In [`MembershipMode::InQueue`], `index` is marked but no pending value
+  /// maps to it.
+  ExtraMember {
+    /// The stray membership index.
+    index: usize,
+  },
+}
+
+impl core::fmt::Display for StateError {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    match self {
+      StateError::Indices(reason) => f.write_str(reason),
+      StateError::OutOfRange { offset, index } => write!(
+        f,
+        "pending value {offset} has index {index} beyond the membership backing"
+      ),
+      StateError::Unmarked { offset, index } => {
+        write!(f, "pending value {offset} has unmarked index {index}")
+      }
+      StateError::Duplicate { offset, index } => {
+        write!(f, "pending value {offset} repeats index {index}")
+      }
+      StateError::ExtraMember { index } => {
+        write!(f, "index {index} is marked but not pending")
+      }
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StateError {}
+
+/// Checks that ring state and membership agree, reporting the first mismatch.
+///
+/// The backing is used as scratch space and restored before returning.
+fn check_state<T, S>(
+  buf: &[T],
+  in_queue: &mut S,
+  mode: MembershipMode,
+  (head, tail, len): (usize, usize, usize),
+) -> Result<(), StateError>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  check_indices(buf.len(), head, tail, len).map_err(StateError::Indices)?;
+  let pending =
+    |offset: usize| -> usize { buf[(head + offset) % buf.len()].into() };
+
+  for offset in 0..len {
+    let index = pending(offset);
+    if index >= in_queue.capacity() {
+      return Err(StateError::OutOfRange { offset, index });
+    }
+    if !in_queue.contains(index) {
+      return Err(StateError::Unmarked { offset, index });
+    }
+  }
+
+  // Unmark each index as it is matched, so a repeated key finds its flag
+  // already clear and, in `InQueue`, anything left marked is a stray.
+  let mut error = None;
+  let mut unmarked = 0;
+  for offset in 0..len {
+    let index = pending(offset);
+    if !in_queue.contains(index) {
+      error = Some(StateError::Duplicate { offset, index });
+      break;
+    }
+    in_queue.remove(index);
+    unmarked = offset + 1;
+  }
+  if error.is_none() && mode == MembershipMode::InQueue {
+    for_each_set_bit(&*in_queue, |index| {
+      error.get_or_insert(StateError::ExtraMember { index });
+    });
+  }
+  for offset in 0..unmarked {
+    in_queue.insert(pending(offset));
+  }
+  error.map_or(Ok(()), Err)
+}
+
+/// Owned snapshot of a queue, produced by [`TinySetQueue::snapshot`].
+///
+/// The queue itself only borrows its storage, so checkpoints go through this
+/// standalone copy instead. Enabled by the `serde` feature, which derives
+/// `Serialize` and `Deserialize` for it.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct QueueState<T> {
+  /// Pending values in insertion order.
+  pub pending: alloc::vec::Vec<T>,
+  /// Every index marked in the membership backing, in ascending order. In
+  /// [`MembershipMode::Visited`] this includes values already popped.
+  pub members: alloc::vec::Vec<usize>,
+  /// Membership mode at snapshot time.
+  pub mode: MembershipMode,
+  /// Processing order at snapshot time.
+  pub order: ProcessingOrder,
+}
+
+/// Scalar state recorded by [`TinySetQueue::capture`].
+///
+/// The pending values and membership flags live in the caller's buffers; this
+/// holds everything else [`TinySetQueue::restore`] needs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CapturedState {
+  /// Number of pending values, stored oldest first.
+  pub len: usize,
+  /// Membership mode at capture time.
+  pub mode: MembershipMode,
+  /// Processing order at capture time.
+  pub order: ProcessingOrder,
+}
+
+/// Reason [`TinySetQueue::capture`] or [`TinySetQueue::restore`] failed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CaptureError {
+  /// A value buffer is shorter than the number of pending values.
+  BufferTooSmall,
+  /// The destination membership backing cannot represent every source index.
+  MembershipTooSmall,
+  /// The pending values and recorded membership contradict each other: a
+  /// value is pending twice or is not recorded as a member, or, in
+  /// [`MembershipMode::InQueue`], an index is recorded but not pending.
+  InconsistentState,
+}
+
+impl core::fmt::Display for CaptureError {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    match self {
+      CaptureError::BufferTooSmall => {
+        f.write_str("buffer is shorter than the pending values")
+      }
+      CaptureError::MembershipTooSmall => {
+        f.write_str("membership backing capacity is too small")
+      }
+      CaptureError::InconsistentState => {
+        f.write_str("pending values and membership disagree")
+      }
+    }
+  }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CaptureError {}
+
+/// Caller-provided log of membership indices inserted since the last clear.
+#[cfg(feature = "scratch_log")]
+struct DirtyLog<'a> {
+  indices: &'a mut [usize],
+  len: usize,
+  /// Set when an insert did not fit; the next clear falls back to a full sweep.
+  overflowed: bool,
+}
+
+#[cfg(feature = "scratch_log")]
+impl<'a> DirtyLog<'a> {
+  #[inline(always)]
+  fn record(&mut self, index: usize) {
+    match self.indices.get_mut(self.len) {
+      Some(slot) => {
+        *slot = index;
+        self.len += 1;
+      }
+      None => self.overflowed = true,
+    }
+  }
+}
+
+/// Controls how membership is tracked when popping values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MembershipMode {
+  /// Membership is cleared upon popping, allowing the value to be enqueued again.
+  InQueue,
+  /// Membership persists after popping, preventing re-enqueueing.
+  Visited,
+}
+
+/// Controls the order in which pending values are popped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProcessingOrder {
+  /// First-in, first-out processing (queue semantics).
+  Fifo,
+  /// Last-in, first-out processing (stack semantics).
+  Lifo,
+  /// Smallest `value.into()` first (a simple monotone priority queue).
+  ///
+  /// The ring stays in insertion order and every `pop` scans it for the
+  /// minimum key, then closes the gap, so `pop` is O(len). For the small
+  /// capacities this crate targets that is usually cheaper than maintaining a
+  /// heap. Iterators walk pending values in insertion order.
+  InsertionMin,
+}
+
+/// Returns the offset from `head` of the pending value with the smallest key.
+fn min_key_offset<T>(buf: &[T], head: usize, len: usize) -> usize
+where
+  T: Copy + Into<usize>,
+{
+  (0..len)
+    .min_by_key(|offset| buf[(head + offset) % buf.len()].into())
+    .unwrap_or(0)
+}
+
+/// Removes the pending value at `offset` from `head`, shifting the newer
+/// values down by one slot, and returns it.
+fn remove_at_offset<T: Copy>(
+  buf: &mut [T],
+  head: usize,
+  len: usize,
+  offset: usize,
+) -> T {
+  let cap = buf.len();
+  let value = buf[(head + offset) % cap];
+  for offset in offset..len - 1 {
+    buf[(head + offset) % cap] = buf[(head + offset + 1) % cap];
+  }
+  value
+}
+
+/// Inserts `value` at `offset` from `head`, shifting the values from `offset`
+/// onwards up by one slot. The inverse of [`remove_at_offset`].
+fn insert_at_offset<T: Copy>(
+  buf: &mut [T],
+  head: usize,
+  len: usize,
+  offset: usize,
+  value: T,
+) {
+  let cap = buf.len();
+  for offset in (offset..len).rev() {
+    buf[(head + offset + 1) % cap] = buf[(head + offset) % cap];
+  }
+  buf[(head + offset) % cap] = value;
+}
+
+/// Reusable construction settings for [`TinySetQueue::new_with_config`].
+///
+/// Naming the fields avoids transposing `mode` and `order`, and presets can be
+/// shared as constants:
+///
+/// ```
+/// use tinysetqueue::{MembershipMode, ProcessingOrder, QueueConfig};
+///
+/// const WORKLIST: QueueConfig =
+///   QueueConfig::new(MembershipMode::InQueue, ProcessingOrder::Fifo);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct QueueConfig {
+  /// Whether membership clears on `pop`.
+  pub mode: MembershipMode,
+  /// Order in which pending values are popped.
+  pub order: ProcessingOrder,
+  /// Whether the membership backing is zeroed on construction.
+  pub clear_on_new: bool,
+}
+
+impl QueueConfig {
+  /// Returns a config with the given mode and order that clears the backing on
+  /// construction.
+  pub const fn new(mode: MembershipMode, order: ProcessingOrder) -> Self {
+    QueueConfig {
+      mode,
+      order,
+      clear_on_new: true,
+    }
+  }
+
+  /// Breadth-first traversal: visit each index once, in FIFO order.
+  pub const fn bfs() -> Self {
+    Self::new(MembershipMode::Visited, ProcessingOrder::Fifo)
+  }
+
+  /// Depth-first traversal: visit each index once, in LIFO order.
+  pub const fn dfs() -> Self {
+    Self::new(MembershipMode::Visited, ProcessingOrder::Lifo)
+  }
+}
+
+/// Step-by-step constructor for [`TinySetQueue`], created by
+/// [`TinySetQueue::builder`].
+///
+/// Mode and order are set by name and default to [`MembershipMode::InQueue`]
+/// and [`ProcessingOrder::Fifo`]:
+///
+/// ```
+/// use tinysetqueue::{MembershipMode, TinySetQueue};
+///
+/// let mut buf = [0u8; 4];
+/// let mut membership = [false; 16];
+/// let mut queue = TinySetQueue::builder(&mut buf, &mut membership)
+///   .mode(MembershipMode::Visited)
+///   .build();
+/// queue.push(3).unwrap();
+/// assert_eq!(queue.pop(), Some(3));
+/// assert!(queue.contains(3));
+/// ```
+pub struct QueueBuilder<'a, T, S>
+where
+  S: SetBacking + ?Sized,
+{
+  buf: &'a mut [T],
+  in_queue: &'a mut S,
+  config: QueueConfig,
+}
+
+impl<'a, T, S> QueueBuilder<'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  /// Sets the membership mode.
+  pub fn mode(mut self, mode: MembershipMode) -> Self {
+    self.config.mode = mode;
+    self
+  }
+
+  /// Sets the processing order.
+  pub fn order(mut self, order: ProcessingOrder) -> Self {
+    self.config.order = order;
+    self
+  }
+
+  /// Builds the queue, clearing the backing if the `clear_on_new` feature is
+  /// enabled, as [`TinySetQueue::new`] does.
+  pub fn build(self) -> TinySetQueue<'a, T, S> {
+    TinySetQueue::new_with_config(self.buf, self.in_queue, self.config)
+  }
+}
+
+/// Membership backing layouts understood by [`estimate_bytes`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BackingKind {
+  /// One `bool` per index, e.g. `[bool; N]`.
+  Bool,
+  /// One bit per index packed into `u64` words, e.g. `[u64; N]`.
+  U64,
+  /// One bit per index packed into `u32` words.
+  U32,
+  /// One bit per index packed into `u128` words.
+  U128,
+  /// One bit per index packed into bytes, e.g. `[u8; N]`.
+  U8,
+}
+
+/// Returns the bytes needed for a queue of `buf_capacity` values of `T` with a
+/// membership backing of kind `backing` covering indices `0..domain`.
+///
+/// This is a pure calculation for planning stack or static budgets; it can be
+/// evaluated in a `const` context and does not include the few words the
+/// queue struct itself occupies.
+pub const fn estimate_bytes<T>(
+  buf_capacity: usize,
+  domain: usize,
+  backing: BackingKind,
+) -> usize {
+  let values = buf_capacity * core::mem::size_of::<T>();
+  let bits = match backing {
+    BackingKind::Bool => return values + domain,
+    BackingKind::U64 => 64,
+    BackingKind::U32 => 32,
+    BackingKind::U128 => 128,
+    BackingKind::U8 => 8,
+  };
+  let words = domain / bits + (domain % bits != 0) as usize;
+  values + words * (bits / 8)
+}
+
+/// Checks that `head`, `tail` and `len` describe a valid window of a ring
+/// with `cap` slots.
+fn check_indices(
+  cap: usize,
+  head: usize,
+  tail: usize,
+  len: usize,
+) -> Result<(), &'static str> {
+  if cap == 0 {
+    if head != 0 || tail != 0 || len != 0 {
+      return Err("zero-capacity queue requires head, tail and len of 0");
+    }
+  } else {
+    if head >= cap {
+      return Err("head is out of bounds");
+    }
+    if tail >= cap {
+      return Err("tail is out of bounds");
+    }
+    if len > cap {
+      return Err("len exceeds capacity");
+    }
+    if (tail + cap - head) % cap != len % cap {
+      return Err("len does not match the distance from head to tail");
+    }
+  }
+  Ok(())
+}
+
+/// The storage and complete ring state of a [`TinySetQueue`].
+///
+/// Produced by [`TinySetQueue::into_parts_full`] and consumed by
+/// [`TinySetQueue::from_parts_full`] to move a queue's exact state between
+/// owners without re-pushing its contents.
+pub struct QueueParts<'a, T, S>
+where
+  S: SetBacking + ?Sized,
+{
+  /// Ring-buffer storage; only the `len` slots starting at `head` are pending.
+  pub buf: &'a mut [T],
+  /// Membership backing, unchanged from the queue.
+  pub in_queue: &'a mut S,
+  /// Slot of the oldest pending value.
+  pub head: usize,
+  /// Slot the next `push` writes to.
+  pub tail: usize,
+  /// Number of pending values.
+  pub len: usize,
+  /// Membership mode of the queue.
+  pub mode: MembershipMode,
+  /// Processing order of the queue.
+  pub order: ProcessingOrder,
+}
+
+/// A fixed-capacity, allocation-free queue with direct-mapped membership tracking.
+///
+/// Values are converted to indices via [`Into<usize>`], so the queue works best when
+/// keys are dense integers in the range `0..N`. Sparse identifiers (e.g. `{5, 1_000_000}`)
+/// require a membership backing large enough to cover the full domain.
+///
+/// # Sizing
+///
+/// This queue never allocates and **never resizes** at runtime. If an index
+/// exceeds the membership capacity, `push` returns an error.
+pub struct TinySetQueue<'a, T, S>
+where
+  S: SetBacking + ?Sized,
+{
+  buf: &'a mut [T],
+  in_queue: &'a mut S,
+  mode: MembershipMode,
+  order: ProcessingOrder,
+  head: usize,
+  tail: usize,
+  len: usize,
+  #[cfg(feature = "audit")]
+  audit: AuditRing,
+  /// Exclusive upper bound on membership indices that may be set; `clear`
+  /// only zeros the backing below it.
+  high_water: usize,
+  #[cfg(feature = "scratch_log")]
+  scratch: Option<DirtyLog<'a>>,
+  /// Set by every membership change; `members_cached` is stale while set.
+  #[cfg(feature = "members_cache")]
+  members_dirty: bool,
+  /// Result of the last `members_count_cached` recount.
+  #[cfg(feature = "members_cache")]
+  members_cached: usize,
+  #[cfg(all(feature = "leak_check", debug_assertions))]
+  leak: LeakCheck,
+  #[cfg(all(feature = "alias_check", debug_assertions))]
+  _alias: alias::BackingToken,
+}
+
+impl<'a, T, S> TinySetQueue<'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  /// Constructs a queue backed by caller-provided storage.
+  ///
+  /// * `buf` supplies the ring-buffer storage used for pending values.
+  /// * `in_queue` is the direct-mapped membership backing (e.g. `[bool]`, `[u64]`).
+  /// * `mode` determines whether membership clears on `pop`.
+  /// * `order` selects FIFO or LIFO processing of queued values.
+  ///
+  /// `in_queue.capacity()` must exceed any index produced by `value.into()`. When the
+  /// `clear_on_new` feature (enabled by default) is active, the backing is cleared to
+  /// prevent stale membership flags.
+  pub fn new(
+    buf: &'a mut [T],
+    in_queue: &'a mut S,
+    mode: MembershipMode,
+    order: ProcessingOrder,
+  ) -> Self {
+    Self::new_with_config(
+      buf,
+      in_queue,
+      QueueConfig {
+        mode,
+        order,
+        clear_on_new: cfg!(feature = "clear_on_new"),
+      },
+    )
+  }
+
+  /// Constructs a queue from a [`QueueConfig`] preset.
+  ///
+  /// Equivalent to [`new`](Self::new) with the config's mode and order, except
+  /// that clearing the backing is decided by `config.clear_on_new` rather than
+  /// by the `clear_on_new` crate feature.
+  pub fn new_with_config(
+    buf: &'a mut [T],
+    in_queue: &'a mut S,
+    config: QueueConfig,
+  ) -> Self {
+    let QueueConfig {
+      mode,
+      order,
+      clear_on_new,
+    } = config;
+    // Without a clear, any existing flag may still be set.
+    let high_water = if clear_on_new {
+      in_queue.clear_all();
+      0
+    } else {
+      in_queue.capacity()
+    };
+    TinySetQueue {
+      #[cfg(all(feature = "alias_check", debug_assertions))]
+      _alias: alias::BackingToken::register(backing_addr(in_queue)),
+      buf,
+      in_queue,
+      mode,
+      order,
+      head: 0,
+      tail: 0,
+      len: 0,
+      #[cfg(feature = "audit")]
+      audit: AuditRing::new(),
+      high_water,
+      #[cfg(feature = "scratch_log")]
+      scratch: None,
+      #[cfg(feature = "members_cache")]
+      members_dirty: true,
+      #[cfg(feature = "members_cache")]
+      members_cached: 0,
+      #[cfg(all(feature = "leak_check", debug_assertions))]
+      leak: LeakCheck::new(mode, true),
+    }
+  }
+
+  /// Starts a [`QueueBuilder`] over caller-provided storage, as a
+  /// self-documenting alternative to the positional arguments of
+  /// [`new`](Self::new).
+  pub fn builder(
+    buf: &'a mut [T],
+    in_queue: &'a mut S,
+  ) -> QueueBuilder<'a, T, S> {
+    QueueBuilder {
+      buf,
+      in_queue,
+      config: QueueConfig {
+        mode: MembershipMode::InQueue,
+        order: ProcessingOrder::Fifo,
+        clear_on_new: cfg!(feature = "clear_on_new"),
+      },
+    }
+  }
+
+  /// Constructs a queue and pushes every value in `seed`, all or nothing.
+  ///
+  /// Duplicates in `seed` are skipped as with [`push`](Self::push). If any
+  /// value is rejected, the queue is [`clear`](Self::clear)ed and returned
+  /// empty alongside the offending value, so callers never observe a partial
+  /// fill.
+  ///
+  /// # Errors
+  ///
+  /// Returns `Err((queue, value))` for the first seed value that is out of
+  /// range or does not fit.
+  // The queue is handed back by value so its borrowed storage can be reused;
+  // with `audit` enabled that makes the error variant large.
+  #[allow(clippy::result_large_err)]
+  pub fn init_with(
+    buf: &'a mut [T],
+    in_queue: &'a mut S,
+    mode: MembershipMode,
+    order: ProcessingOrder,
+    seed: &[T],
+  ) -> Result<Self, (Self, T)> {
+    let mut queue = Self::new(buf, in_queue, mode, order);
+    for &value in seed {
+      if let Err(error) = queue.push(value) {
+        queue.clear();
+        return Err((queue, error.into_inner()));
+      }
+    }
+    Ok(queue)
+  }
+
+  /// Constructs a queue that records every membership insert in `scratch` so
+  /// that [`clear`](Self::clear) only resets the recorded indices.
+  ///
+  /// This keeps clears proportional to the work done rather than to the size
+  /// of the membership backing. When more indices are inserted between clears
+  /// than `scratch` can hold, the next clear falls back to zeroing the backing.
+  /// Without the `clear_on_new` feature the first clear is always a full one,
+  /// since flags set before construction were never recorded. Enabled by the
+  /// `scratch_log` feature.
+  #[cfg(feature = "scratch_log")]
+  pub fn new_with_scratch(
+    buf: &'a mut [T],
+    in_queue: &'a mut S,
+    scratch: &'a mut [usize],
+    mode: MembershipMode,
+    order: ProcessingOrder,
+  ) -> Self {
+    let mut queue = Self::new(buf, in_queue, mode, order);
+    let overflowed = queue.high_water != 0;
+    queue.scratch = Some(DirtyLog {
+      indices: scratch,
+      len: 0,
+      overflowed,
+    });
+    queue
+  }
+
+  /// Assembles a queue from existing ring state without clearing membership.
+  fn from_raw(
+    buf: &'a mut [T],
+    in_queue: &'a mut S,
+    mode: MembershipMode,
+    order: ProcessingOrder,
+    (head, tail, len): (usize, usize, usize),
+  ) -> Self {
+    let high_water = in_queue.capacity();
+    TinySetQueue {
+      #[cfg(all(feature = "alias_check", debug_assertions))]
+      _alias: alias::BackingToken::register(backing_addr(in_queue)),
+      buf,
+      in_queue,
+      mode,
+      order,
+      head,
+      tail,
+      len,
+      #[cfg(feature = "audit")]
+      audit: AuditRing::new(),
+      high_water,
+      #[cfg(feature = "scratch_log")]
+      scratch: None,
+      #[cfg(feature = "members_cache")]
+      members_dirty: true,
+      #[cfg(feature = "members_cache")]
+      members_cached: 0,
+      // Views over owned storage are routinely dropped while non-empty.
+      #[cfg(all(feature = "leak_check", debug_assertions))]
+      leak: LeakCheck::new(mode, false),
+    }
+  }
+
+  /// Clears the queue without freeing any backing storage.
+  ///
+  /// All membership flags are reset and the queue becomes empty. Only the part
+  /// of the backing below the highest index inserted since the last clear is
+  /// zeroed, so frequent clears of a large, sparsely used backing stay cheap.
+  pub fn clear(&mut self) {
+    #[cfg(feature = "scratch_log")]
+    match &mut self.scratch {
+      Some(log) if !log.overflowed => {
+        for &index in log.indices[..log.len].iter() {
+          self.in_queue.remove(index);
+        }
+        log.len = 0;
+      }
+      Some(log) => {
+        self.in_queue.clear_up_to(self.high_water);
+        log.len = 0;
+        log.overflowed = false;
+      }
+      None => self.in_queue.clear_up_to(self.high_water),
+    }
+    #[cfg(not(feature = "scratch_log"))]
+    self.in_queue.clear_up_to(self.high_water);
+    #[cfg(feature = "members_cache")]
+    {
+      self.members_dirty = false;
+      self.members_cached = 0;
+    }
+    self.high_water = 0;
+    self.head = 0;
+    self.tail = 0;
+    self.len = 0;
+    self.sync_leak_check();
+    #[cfg(feature = "audit")]
+    self.audit.clear();
+  }
+
+  /// Discards every pending value while keeping the membership record.
+  ///
+  /// Meant for [`MembershipMode::Visited`] reuse across passes, e.g.
+  /// multi-source BFS: values already seen stay rejected, and the discarded
+  /// values stay marked as if they had been popped. In
+  /// [`MembershipMode::InQueue`] the discarded values' flags are cleared
+  /// instead, so no stale "in queue" flag outlives them. The cost is O(len)
+  /// in `InQueue` mode and O(1) otherwise, without touching the rest of the
+  /// backing.
+  pub fn clear_pending(&mut self) {
+    if matches!(self.mode, MembershipMode::InQueue) {
+      let cap = self.buf.len();
+      for offset in 0..self.len {
+        self.unmark(self.buf[(self.head + offset) % cap].into());
+      }
+    }
+    self.head = 0;
+    self.tail = 0;
+    self.len = 0;
+    self.sync_leak_check();
+  }
+
+  /// Prepares the queue for another run in one call: [`clear`](Self::clear)s
+  /// it and, when `config` is `Some`, switches to its mode and order.
+  ///
+  /// The clear takes the same shortcuts as `clear`, resetting only the
+  /// high-water part of the backing (or, with the `scratch_log` feature, the
+  /// logged indices) and emptying any scratch log. `config.clear_on_new` is
+  /// ignored because recycling always clears.
+  pub fn recycle(&mut self, config: Option<QueueConfig>) {
+    if let Some(config) = config {
+      self.mode = config.mode;
+      self.order = config.order;
+    }
+    self.clear();
+  }
+
+  /// Switches to `mode` and `order` and [`clear`](Self::clear)s the queue.
+  ///
+  /// This wipes all state: pending values are dropped, the ring indices reset,
+  /// and every membership flag is cleared, including the record of values
+  /// already popped in [`MembershipMode::Visited`]. Use
+  /// [`set_mode`](Self::set_mode) or [`set_order`](Self::set_order) to switch
+  /// while keeping the queue's contents.
+  pub fn reset_to(&mut self, mode: MembershipMode, order: ProcessingOrder) {
+    self.mode = mode;
+    self.order = order;
+    self.clear();
+  }
+
+  /// Clears the membership flag for `index`.
+  #[inline(always)]
+  fn unmark(&mut self, index: usize) {
+    self.in_queue.remove(index);
+    self.invalidate_members_count();
+  }
+
+  /// Marks the cached `members_count_cached` result stale after a membership
+  /// change.
+  #[inline(always)]
+  fn invalidate_members_count(&mut self) {
+    #[cfg(feature = "members_cache")]
+    {
+      self.members_dirty = true;
+    }
+  }
+
+  /// Arms or disarms the `leak_check` guard.
+  #[inline(always)]
+  fn set_leak_check_enabled(&mut self, _enabled: bool) {
+    #[cfg(all(feature = "leak_check", debug_assertions))]
+    {
+      self.leak.enabled = _enabled;
+      self.sync_leak_check();
+    }
+  }
+
+  /// Returns the value the next `pop` would yield, without removing it.
+  ///
+  /// Nothing is modified: the ring indices and membership are untouched.
+  /// Returns `None` when the queue is empty. Under
+  /// [`ProcessingOrder::InsertionMin`] this scans for the smallest key.
+  pub fn peek(&self) -> Option<&T> {
+    if self.is_empty() {
+      return None;
+    }
+    Some(&self.buf[(self.head + self.next_offset()) % self.buf.len()])
+  }
+
+  /// Returns the insertion-order offset from `head` of the value the next
+  /// `pop` takes. The queue must not be empty.
+  fn next_offset(&self) -> usize {
+    match self.order {
+      ProcessingOrder::Fifo => 0,
+      ProcessingOrder::Lifo => self.len - 1,
+      ProcessingOrder::InsertionMin => {
+        min_key_offset(self.buf, self.head, self.len)
+      }
+    }
+  }
+
+  /// Returns the oldest pending value, whatever the processing order.
+  #[inline]
+  pub fn front(&self) -> Option<&T> {
+    if self.is_empty() {
+      return None;
+    }
+    Some(&self.buf[self.head])
+  }
+
+  /// Returns the newest pending value, whatever the processing order.
+  #[inline]
+  pub fn back(&self) -> Option<&T> {
+    if self.is_empty() {
+      return None;
+    }
+    Some(&self.buf[(self.tail + self.buf.len() - 1) % self.buf.len()])
+  }
+
+  /// Returns the pending value at `logical` positions from the pop side, or
+  /// `None` if `logical >= len`.
+  ///
+  /// Position 0 is the value the next `pop` takes under FIFO (the oldest) and
+  /// LIFO (the newest), so `get(i)` agrees with `iter().nth(i)`. Under
+  /// [`ProcessingOrder::InsertionMin`] positions follow insertion order, like
+  /// [`iter`](Self::iter).
+  #[inline]
+  pub fn get(&self, logical: usize) -> Option<&T> {
+    if logical >= self.len {
+      return None;
+    }
+    let offset = match self.order {
+      ProcessingOrder::Fifo | ProcessingOrder::InsertionMin => logical,
+      ProcessingOrder::Lifo => self.len - 1 - logical,
+    };
+    Some(&self.buf[(self.head + offset) % self.buf.len()])
+  }
+
+  /// Returns `true` when `value` is marked in the membership backing.
+  ///
+  /// In [`MembershipMode::InQueue`] this means `value` is pending. In
+  /// [`MembershipMode::Visited`] the flag outlives the buffer, so this also
+  /// returns `true` for values that were pushed and already popped, which
+  /// makes the queue usable as a "seen" set. Values whose index is beyond the
+  /// membership backing return `false`.
+  pub fn contains(&self, value: T) -> bool {
+    let idx: usize = value.into();
+    idx < self.in_queue.capacity() && self.in_queue.contains(idx)
+  }
+
+  /// Returns `true` when `value` is marked in the membership backing; the same
+  /// check as [`contains`](Self::contains) under a name that reads well in
+  /// [`MembershipMode::Visited`], where the mark means "pushed at some point",
+  /// not "still pending". See [`is_pending`](Self::is_pending).
+  #[inline]
+  pub fn is_visited(&self, value: T) -> bool {
+    self.contains(value)
+  }
+
+  /// Returns `true` only when `value` currently occupies a buffer slot.
+  ///
+  /// In [`MembershipMode::InQueue`] the membership flag already means this, so
+  /// the check is O(1). In [`MembershipMode::Visited`] a marked value may have
+  /// been popped, so this scans the pending values: O(len) for marked values,
+  /// O(1) for unmarked ones.
+  pub fn is_pending(&self, value: T) -> bool {
+    if !self.contains(value) {
+      return false;
+    }
+    match self.mode {
+      MembershipMode::InQueue => true,
+      MembershipMode::Visited => {
+        let idx: usize = value.into();
+        self.iter().any(|pending| (*pending).into() == idx)
+      }
+    }
+  }
+
+  /// Sets the membership flag for `index` and records it for the next clear.
+  #[inline(always)]
+  fn mark(&mut self, index: usize) {
+    self.in_queue.insert(index);
+    self.invalidate_members_count();
+    self.high_water = self.high_water.max(index + 1);
+    #[cfg(feature = "scratch_log")]
+    if let Some(log) = &mut self.scratch {
+      log.record(index);
+    }
+  }
+
+  /// Mirrors the pending count and mode into the `leak_check` guard.
+  #[inline(always)]
+  fn sync_leak_check(&mut self) {
+    #[cfg(all(feature = "leak_check", debug_assertions))]
+    {
+      self.leak.pending = self.len;
+      self.leak.in_queue_mode = matches!(self.mode, MembershipMode::InQueue);
+    }
+  }
+
+  /// Returns the maximum number of pending items the queue can hold.
+  ///
+  /// This is the ring buffer length; the range of values that can be pushed
+  /// is [`membership_capacity`](Self::membership_capacity).
+  #[inline]
+  pub fn capacity(&self) -> usize {
+    self.buf.len()
+  }
+
+  /// Returns the number of indices the membership backing can represent.
+  ///
+  /// Every value pushed must satisfy `value.into() < membership_capacity()`,
+  /// however much room [`capacity`](Self::capacity) leaves: a push beyond it
+  /// fails with [`PushError::OutOfRange`] even when the queue is empty.
+  #[inline]
+  pub fn membership_capacity(&self) -> usize {
+    self.in_queue.capacity()
+  }
+
+  /// Checks up front that the membership backing covers every index up to
+  /// and including `max_index`, the largest key the caller will push.
+  ///
+  /// # Errors
+  ///
+  /// Returns `Err` with the backing's capacity when `max_index` does not fit,
+  /// for use in a panic or log message.
+  #[inline]
+  pub fn validate_capacity(&self, max_index: usize) -> Result<(), usize> {
+    let capacity = self.in_queue.capacity();
+    if max_index >= capacity {
+      return Err(capacity);
+    }
+    Ok(())
+  }
+
+  /// Returns the number of items currently enqueued.
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Returns `true` when the queue is empty.
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Returns `true` when the queue is at full capacity.
+  #[inline]
+  pub fn is_full(&self) -> bool {
+    self.len == self.buf.len()
+  }
+
+  /// Returns how many more values fit before the queue is full.
+  #[inline]
+  pub fn remaining(&self) -> usize {
+    debug_assert!(self.len <= self.buf.len());
+    self.buf.len() - self.len
+  }
+
+  /// Returns `true` when a [`push_front`](Self::push_front) of a new value
+  /// would find a free slot.
+  ///
+  /// Front and back pushes draw from the same capacity, so this currently
+  /// equals [`can_push_back`](Self::can_push_back) and `!is_full()`.
+  #[inline]
+  pub fn can_push_front(&self) -> bool {
+    !self.is_full()
+  }
+
+  /// Returns `true` when a [`push`](Self::push) of a new value would find a
+  /// free slot.
+  ///
+  /// Front and back pushes draw from the same capacity.
+  #[inline]
+  pub fn can_push_back(&self) -> bool {
+    !self.is_full()
+  }
+
+  /// Pushes a value into the queue unless it is already present.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`PushError::OutOfRange`] if `value.into()` exceeds the bounds of
+  /// the membership backing, or [`PushError::Full`] if the queue is full and
+  /// the value is not already present. Both carry the rejected value.
+  pub fn push(&mut self, value: T) -> Result<PushResult, PushError<T>> {
+    self.push_indexed(value.into(), value)
+  }
+
+  /// Pushes `value` using a caller-supplied membership `index` instead of
+  /// calling `value.into()`.
+  ///
+  /// This is for key types whose conversion is expensive or whose index is
+  /// naturally computed alongside the value. The caller **must** guarantee that
+  /// `index == value.into()`: `pop` and every other method derive the index from
+  /// the stored value, so a mismatch leaves membership flags set or cleared for
+  /// the wrong index. Debug builds check the contract with an assertion.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`PushError::OutOfRange`] if `index` is not below
+  /// [`membership_capacity`](Self::membership_capacity), or
+  /// [`PushError::Full`] if the queue is full and the value is not already
+  /// present.
+  pub fn push_mapped(
+    &mut self,
+    index: usize,
+    value: T,
+  ) -> Result<PushResult, PushError<T>> {
+    debug_assert_eq!(index, value.into(), "index must equal value.into()");
+    self.push_indexed(index, value)
+  }
+
+  /// Shared body of [`push`](Self::push) and
+  /// [`push_mapped`](Self::push_mapped); `index` must equal `value.into()`.
+  fn push_indexed(
+    &mut self,
+    index: usize,
+    value: T,
+  ) -> Result<PushResult, PushError<T>> {
+    if index >= self.in_queue.capacity() {
+      return Err(PushError::OutOfRange(value));
+    }
+
+    if self.in_queue.contains(index) {
+      return Ok(PushResult::AlreadyPresent);
+    }
+
+    if self.is_full() {
+      return Err(PushError::Full(value));
+    }
+
+    self.buf[self.tail] = value;
+    self.mark(index);
+
+    self.tail = (self.tail + 1) % self.buf.len();
+    self.len += 1;
+    self.sync_leak_check();
+
+    Ok(PushResult::Inserted)
+  }
+
+  /// Pushes a value the caller has already checked fits, skipping the range
+  /// and capacity checks of [`push`](Self::push).
+  ///
+  /// Duplicates are still detected and yield [`PushResult::AlreadyPresent`].
+  ///
+  /// # Panics
+  ///
+  /// Passing a value whose index is out of range is a logic error that
+  /// panics on the membership lookup. Pushing a new value into a full queue
+  /// is also a logic error: it panics in debug builds and otherwise
+  /// overwrites the oldest pending value and leaves the queue inconsistent.
+  /// No undefined behavior is possible either way.
+  pub fn push_unchecked(&mut self, value: T) -> PushResult {
+    let index: usize = value.into();
+    if self.in_queue.contains(index) {
+      return PushResult::AlreadyPresent;
+    }
+    debug_assert!(!self.is_full(), "push_unchecked on a full queue");
+
+    self.buf[self.tail] = value;
+    self.mark(index);
+
+    self.tail = (self.tail + 1) % self.buf.len();
+    self.len += 1;
+    self.sync_leak_check();
+
+    PushResult::Inserted
+  }
+
+  /// Pops the next value according to the configured processing order, if any.
+  ///
+  /// Membership is cleared in [`MembershipMode::InQueue`] and retained in
+  /// [`MembershipMode::Visited`].
+  pub fn pop(&mut self) -> Option<T> {
+    if self.is_empty() {
+      return None;
+    }
+
+    let value = match self.order {
+      ProcessingOrder::Fifo => {
+        let idx = self.head;
+        self.head = (self.head + 1) % self.buf.len();
+        self.buf[idx]
+      }
+      ProcessingOrder::Lifo => {
+        debug_assert!(!self.buf.is_empty());
+        let idx = if self.tail == 0 {
+          self.buf.len() - 1
+        } else {
+          self.tail - 1
+        };
+        self.tail = idx;
+        self.buf[idx]
+      }
+      ProcessingOrder::InsertionMin => {
+        let offset = min_key_offset(self.buf, self.head, self.len);
+        let value = remove_at_offset(self.buf, self.head, self.len, offset);
+        self.tail = (self.tail + self.buf.len() - 1) % self.buf.len();
+        value
+      }
+    };
+
+    Some(self.finish_pop(value))
+  }
+
+  /// Pops the oldest pending value, whatever the processing order.
+  ///
+  /// Together with [`push_front`](Self::push_front) and
+  /// [`push_back`](Self::push_back) this gives double-ended access, e.g. for
+  /// 0-1 BFS. Membership is handled as in [`pop`](Self::pop).
+  pub fn pop_front(&mut self) -> Option<T> {
+    if self.is_empty() {
+      return None;
+    }
+    let value = self.buf[self.head];
+    self.head = (self.head + 1) % self.buf.len();
+    Some(self.finish_pop(value))
+  }
+
+  /// Pops the newest pending value, whatever the processing order.
+  ///
+  /// Membership is handled as in [`pop`](Self::pop).
+  pub fn pop_back(&mut self) -> Option<T> {
+    if self.is_empty() {
+      return None;
+    }
+    self.tail = (self.tail + self.buf.len() - 1) % self.buf.len();
+    Some(self.finish_pop(self.buf[self.tail]))
+  }
+
+  /// Updates membership and `len` for a value just taken out of the ring.
+  fn finish_pop(&mut self, value: T) -> T {
+    let idx: usize = value.into();
+
+    match self.mode {
+      MembershipMode::InQueue => self.unmark(idx),
+      #[cfg(feature = "audit")]
+      MembershipMode::Visited => self.audit.record(idx),
+      #[cfg(not(feature = "audit"))]
+      MembershipMode::Visited => {}
+    }
+
+    self.len -= 1;
+    self.sync_leak_check();
+
+    value
+  }
+
+  /// Returns an iterator over the pending values in processing order.
+  ///
+  /// The iterator borrows the queue immutably and never touches membership.
+  pub fn iter(&self) -> Iter<'_, T> {
+    Iter::new(self.buf, self.head, self.len, self.order)
+  }
+
+  /// Returns an iterator over `(value.into(), &value)` pairs in processing order.
+  pub fn iter_indexed(&self) -> impl Iterator<Item = (usize, &T)> + '_ {
+    self.iter().map(|value| ((*value).into(), value))
+  }
+
+  /// Pops the next value only if `pred` accepts it.
+  ///
+  /// When `pred` returns `false` (or the queue is empty) nothing changes and
+  /// `None` is returned. Membership is updated per the configured
+  /// [`MembershipMode`] when the value is popped.
+  pub fn pop_if<F>(&mut self, pred: F) -> Option<T>
+  where
+    F: FnOnce(&T) -> bool,
+  {
+    if pred(self.peek()?) {
+      self.pop()
+    } else {
+      None
+    }
+  }
+
+  /// Pops the next value behind a guard that restores it unless committed.
+  ///
+  /// If the returned [`PopGuard`] is dropped without calling
+  /// [`PopGuard::commit`] (including during a panic unwind or an early return),
+  /// the value is put back at the front of the processing order and its
+  /// membership is restored, leaving the queue as it was before the pop.
+  pub fn pop_guarded(&mut self) -> Option<PopGuard<'_, 'a, T, S>> {
+    if self.is_empty() {
+      return None;
+    }
+    let offset = self.next_offset();
+    let value = self.pop()?;
+    Some(PopGuard {
+      queue: self,
+      value,
+      offset,
+      committed: false,
+    })
+  }
+
+  /// Reinserts a just-popped value at `offset` from `head` in insertion order,
+  /// where [`next_offset`](Self::next_offset) located it before the pop.
+  fn unpop(&mut self, value: T, offset: usize) {
+    debug_assert!(!self.is_full());
+    debug_assert!(offset <= self.len);
+    if offset == 0 {
+      self.head = (self.head + self.buf.len() - 1) % self.buf.len();
+      self.buf[self.head] = value;
+    } else {
+      insert_at_offset(self.buf, self.head, self.len, offset, value);
+      self.tail = (self.tail + 1) % self.buf.len();
+    }
+    self.mark(value.into());
+    self.len += 1;
+    self.sync_leak_check();
+    #[cfg(feature = "audit")]
+    if matches!(self.mode, MembershipMode::Visited) {
+      self.audit.forget_last(value.into());
+    }
+  }
+
+  /// Returns the pending values as two slices, like `VecDeque::as_slices`.
+  ///
+  /// The first slice runs from the oldest value towards the end of the
+  /// buffer and the second holds the values that wrapped around to its start,
+  /// so together they list the values in insertion order (FIFO processing
+  /// order; LIFO pops from the end of the second slice). The second slice is
+  /// empty when the values do not wrap.
+  pub fn as_slices(&self) -> (&[T], &[T]) {
+    let first_len = self.len.min(self.buf.len() - self.head);
+    (
+      &self.buf[self.head..self.head + first_len],
+      &self.buf[..self.len - first_len],
+    )
+  }
+
+  /// Rotates the ring so the pending values occupy `buf[..len]` in insertion
+  /// order, and returns that slice.
+  ///
+  /// The rotation touches the whole buffer; see [`normalize`](Self::normalize)
+  /// for a cheaper path when the values do not wrap.
+  pub fn make_contiguous(&mut self) -> &[T] {
+    if !self.buf.is_empty() {
+      self.buf.rotate_left(self.head);
+      self.head = 0;
+      self.tail = self.len % self.buf.len();
+    }
+    &self.buf[..self.len]
+  }
+
+  /// Moves the pending values to the start of the buffer, preserving order.
+  ///
+  /// When the values do not wrap around the end of the ring they are copied
+  /// down with a single `copy_within`; only wrapped contents fall back to the
+  /// full [`make_contiguous`](Self::make_contiguous) rotation. Afterwards the
+  /// pending values occupy `buf[..len]`.
+  pub fn normalize(&mut self) {
+    if self.head + self.len <= self.buf.len() {
+      self.buf.copy_within(self.head..self.head + self.len, 0);
+      self.head = 0;
+      self.tail = if self.buf.is_empty() {
+        0
+      } else {
+        self.len % self.buf.len()
+      };
+    } else {
+      self.make_contiguous();
+    }
+  }
+
+  /// Moves the pending values to `buf[..len]` like
+  /// [`normalize`](Self::normalize), and reports whether they wrapped around
+  /// the end of the ring and needed a full rotation.
+  ///
+  /// Counting `true` results under a workload shows how often the ring wraps,
+  /// which helps when tuning the buffer size.
+  pub fn make_contiguous_reporting(&mut self) -> bool {
+    let wrapped = self.head + self.len > self.buf.len();
+    self.normalize();
+    wrapped
+  }
+
+  /// Pops up to `N` values in processing order into a stack array.
+  ///
+  /// Returns the array together with the number of values actually popped; slots
+  /// past that count hold `T::default()`. Membership is updated per the configured
+  /// [`MembershipMode`], exactly as with [`pop`](Self::pop).
+  pub fn take_front<const N: usize>(&mut self) -> ([T; N], usize)
+  where
+    T: Default,
+  {
+    let mut out = [T::default(); N];
+    let mut count = 0;
+    while count < N {
+      match self.pop() {
+        Some(value) => {
+          out[count] = value;
+          count += 1;
+        }
+        None => break,
+      }
+    }
+    (out, count)
+  }
+
+  /// Returns `true` when every index present in `other` is also present in this
+  /// queue's membership backing.
+  ///
+  /// The comparison spans the larger of the two capacities; indices beyond a
+  /// backing's capacity are treated as clear.
+  pub fn members_superset_of<O>(&self, other: &O) -> bool
+  where
+    O: SetBacking + ?Sized,
+  {
+    let chunks = chunk_count(self.in_queue.capacity().max(other.capacity()));
+    (0..chunks)
+      .all(|chunk| other.chunk64(chunk) & !self.in_queue.chunk64(chunk) == 0)
+  }
+
+  /// Returns `true` when every index present in this queue's membership backing is
+  /// also present in `other`.
+  ///
+  /// Uses the same out-of-range convention as [`members_superset_of`](Self::members_superset_of).
+  pub fn members_subset_of<O>(&self, other: &O) -> bool
+  where
+    O: SetBacking + ?Sized,
+  {
+    let chunks = chunk_count(self.in_queue.capacity().max(other.capacity()));
+    (0..chunks)
+      .all(|chunk| self.in_queue.chunk64(chunk) & !other.chunk64(chunk) == 0)
+  }
+
+  /// Counts the indices marked in both this queue's membership backing and `other`.
+  ///
+  /// Only the range covered by both backings can overlap; indices beyond either
+  /// capacity are treated as clear.
+  pub fn members_intersection_count<O>(&self, other: &O) -> usize
+  where
+    O: SetBacking + ?Sized,
+  {
+    let chunks = chunk_count(self.in_queue.capacity().min(other.capacity()));
+    (0..chunks)
+      .map(|chunk| {
+        (self.in_queue.chunk64(chunk) & other.chunk64(chunk)).count_ones()
+          as usize
+      })
+      .sum()
+  }
+
+  /// Fills `out` with the indices present in this queue's membership backing but
+  /// not in `other`.
+  ///
+  /// `out` is cleared first. Indices beyond `other`'s capacity are treated as
+  /// clear in `other`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `out.capacity()` is smaller than this queue's membership capacity.
+  pub fn members_difference_into<O, D>(&self, other: &O, out: &mut D)
+  where
+    O: SetBacking + ?Sized,
+    D: SetBacking + ?Sized,
+  {
+    assert!(
+      out.capacity() >= self.in_queue.capacity(),
+      "output backing is smaller than the membership backing"
+    );
+    out.clear_all();
+    for chunk in 0..chunk_count(self.in_queue.capacity()) {
+      let mut bits = self.in_queue.chunk64(chunk) & !other.chunk64(chunk);
+      while bits != 0 {
+        out.insert((chunk << 6) + bits.trailing_zeros() as usize);
+        bits &= bits - 1;
+      }
+    }
+  }
+
+  /// Fills `out` with the indices present in exactly one of this queue's
+  /// membership backing and `other`.
+  ///
+  /// `out` is cleared first. The backings may differ in capacity; indices
+  /// beyond a backing's capacity are treated as clear in it.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `out.capacity()` is smaller than the larger of the two
+  /// capacities.
+  pub fn members_xor_into<O, D>(&self, other: &O, out: &mut D)
+  where
+    O: SetBacking + ?Sized,
+    D: SetBacking + ?Sized,
+  {
+    let capacity = self.in_queue.capacity().max(other.capacity());
+    assert!(
+      out.capacity() >= capacity,
+      "output backing is smaller than the compared backings"
+    );
+    out.clear_all();
+    for chunk in 0..chunk_count(capacity) {
+      let mut bits = self.in_queue.chunk64(chunk) ^ other.chunk64(chunk);
+      while bits != 0 {
+        out.insert((chunk << 6) + bits.trailing_zeros() as usize);
+        bits &= bits - 1;
+      }
+    }
+  }
+
+  /// Calls `f` with every index marked in the membership backing, in ascending
+  /// order.
+  ///
+  /// Bitset backings are scanned a word at a time, skipping clear words.
+  pub fn members_for_each<F>(&self, f: F)
+  where
+    F: FnMut(usize),
+  {
+    for_each_set_bit(&*self.in_queue, f);
+  }
+
+  /// Returns an iterator over every index marked in the membership backing,
+  /// in ascending order.
+  ///
+  /// In [`MembershipMode::Visited`] this is the set reached so far, including
+  /// values already popped; in [`MembershipMode::InQueue`] it is the pending
+  /// set. See [`SetBacking::iter_set_bits`].
+  pub fn visited_indices(&self) -> SetBitsIter<'_, S> {
+    self.in_queue.iter_set_bits()
+  }
+
+  /// Drains every pending value and yields their indices in ascending order.
+  ///
+  /// Membership is updated per the configured [`MembershipMode`] exactly as if each
+  /// value had been popped. Processing indices in ascending order keeps downstream
+  /// array writes sequential.
+  ///
+  /// This allocates a `Vec` of `len()` indices and sorts it (`O(n log n)`).
+  #[cfg(feature = "alloc")]
+  pub fn drain_indices_sorted(&mut self) -> impl Iterator<Item = usize> {
+    let mut indices = alloc::vec::Vec::with_capacity(self.len);
+    while let Some(value) = self.pop() {
+      indices.push(value.into());
+    }
+    indices.sort_unstable();
+    indices.into_iter()
+  }
+
+  /// Pops up to `out.len()` values into `out`, sorts them by index and returns
+  /// how many were written.
+  ///
+  /// Membership is updated per the configured [`MembershipMode`] exactly as if
+  /// each value had been popped. The written prefix `out[..count]` is sorted
+  /// in place by `value.into()` without allocating; the rest of `out` is left
+  /// untouched. Unlike `drain_indices_sorted`, this returns the values
+  /// themselves and works in `no_std`.
+  pub fn drain_into_sorted(&mut self, out: &mut [T]) -> usize {
+    let mut count = 0;
+    for slot in out.iter_mut() {
+      match self.pop() {
+        Some(value) => *slot = value,
+        None => break,
+      }
+      count += 1;
+    }
+    out[..count].sort_unstable_by_key(|value| (*value).into());
+    count
+  }
+
+  /// Folds over the pending values in processing order without draining them.
+  ///
+  /// Equivalent to `self.iter().fold(init, f)`; membership is never touched.
+  pub fn fold_pending<B, F>(&self, init: B, f: F) -> B
+  where
+    F: FnMut(B, &T) -> B,
+  {
+    self.iter().fold(init, f)
+  }
+
+  /// Pops up to `n` values in processing order into a new `Vec`.
+  ///
+  /// Returns fewer than `n` values if the queue empties first. Membership is
+  /// updated per the configured [`MembershipMode`], exactly as with
+  /// [`pop`](Self::pop).
+  #[cfg(feature = "alloc")]
+  pub fn pop_n(&mut self, n: usize) -> alloc::vec::Vec<T> {
+    let mut out = alloc::vec::Vec::with_capacity(n.min(self.len));
+    while out.len() < n {
+      match self.pop() {
+        Some(value) => out.push(value),
+        None => break,
+      }
+    }
+    out
+  }
+
+  /// Overwrites the ring indices after validating that they are consistent.
+  ///
+  /// `head` is the slot of the oldest pending value, `tail` the slot the next
+  /// `push` writes to, and `len` the number of pending values. The indices must
+  /// lie inside the buffer (all zero for a zero-capacity queue), `len` must not
+  /// exceed the capacity, and `tail - head` must equal `len` modulo the
+  /// capacity. Buffer contents and membership are left as-is; keeping them in
+  /// agreement with the new window is the caller's responsibility.
+  ///
+  /// # Errors
+  ///
+  /// Returns a description of the first violated constraint and leaves the
+  /// queue unchanged.
+  pub fn set_indices(
+    &mut self,
+    head: usize,
+    tail: usize,
+    len: usize,
+  ) -> Result<(), &'static str> {
+    check_indices(self.buf.len(), head, tail, len)?;
+
+    self.head = head;
+    self.tail = tail;
+    self.len = len;
+    self.sync_leak_check();
+    Ok(())
+  }
+
+  /// Pushes a value at the back of the ring (the end [`ProcessingOrder::Lifo`]
+  /// pops from) unless it is already present. Same as [`push`](Self::push).
+  ///
+  /// # Errors
+  ///
+  /// Returns a [`PushError`] under the same conditions as
+  /// [`push`](Self::push).
+  #[inline]
+  pub fn push_back(&mut self, value: T) -> Result<PushResult, PushError<T>> {
+    self.push(value)
+  }
+
+  /// Pushes a value at the front of the ring (the end [`ProcessingOrder::Fifo`]
+  /// pops from) unless it is already present.
+  ///
+  /// Front and back pushes share the same capacity and membership dedup.
+  ///
+  /// # Errors
+  ///
+  /// Returns a [`PushError`] under the same conditions as
+  /// [`push`](Self::push).
+  pub fn push_front(&mut self, value: T) -> Result<PushResult, PushError<T>> {
+    let idx: usize = value.into();
+
+    if idx >= self.in_queue.capacity() {
+      return Err(PushError::OutOfRange(value));
+    }
+
+    if self.in_queue.contains(idx) {
+      return Ok(PushResult::AlreadyPresent);
+    }
+
+    if self.is_full() {
+      return Err(PushError::Full(value));
+    }
+
+    self.head = (self.head + self.buf.len() - 1) % self.buf.len();
+    self.buf[self.head] = value;
+    self.mark(idx);
+    self.len += 1;
+    self.sync_leak_check();
+
+    Ok(PushResult::Inserted)
+  }
+
+  /// Pushes a value at the front of the ring; equivalent to
+  /// [`push_front`](Self::push_front), which also reports a [`PushError`].
+  ///
+  /// # Errors
+  ///
+  /// Returns a [`PushError`] under the same conditions as
+  /// [`push`](Self::push).
+  #[inline]
+  pub fn try_push_front(
+    &mut self,
+    value: T,
+  ) -> Result<PushResult, PushError<T>> {
+    self.push_front(value)
+  }
+
+  /// Returns the indices most recently popped in [`MembershipMode::Visited`],
+  /// oldest first.
+  ///
+  /// Those indices stay marked after popping, so re-pushing them reports
+  /// [`PushResult::AlreadyPresent`]. At most [`AUDIT_CAPACITY`] entries are
+  /// kept; [`clear`](Self::clear) resets the record along with membership.
+  #[cfg(feature = "audit")]
+  pub fn recently_popped(&self) -> &[usize] {
+    self.audit.as_slice()
+  }
+
+  /// Copies the queue's state into a freshly allocated [`OwnedTinySetQueue`].
+  ///
+  /// The owned queue keeps the same capacity, mode, order and ring layout, so it
+  /// replays the same `pop` sequence. Its bitset membership domain covers
+  /// every index the borrowed backing can represent, rounded up to whole
+  /// `u64` words, so any key valid before the conversion stays valid after
+  /// it. Every marked index (pending or, in [`MembershipMode::Visited`],
+  /// already visited) is carried over.
+  #[cfg(feature = "alloc")]
+  pub fn into_owned(&self) -> OwnedTinySetQueue<T> {
+    OwnedTinySetQueue {
+      buf: self.buf.to_vec(),
+      in_queue: (0..chunk_count(self.in_queue.capacity()))
+        .map(|chunk| self.in_queue.chunk64(chunk))
+        .collect(),
+      mode: self.mode,
+      order: self.order,
+      head: self.head,
+      tail: self.tail,
+      len: self.len,
+    }
+  }
+
+  /// Exchanges the pending values and membership state of two queues.
+  ///
+  /// This is the level swap of a two-frontier BFS: after the call each queue
+  /// holds the other's pending values (in the same processing sequence) and
+  /// membership flags, while keeping its own buffer, mode and order. Both rings
+  /// are normalized so pending values start at slot 0. Membership is exchanged
+  /// bit by bit, touching only the words where the two backings differ.
+  ///
+  /// # Errors
+  ///
+  /// Fails without modifying either queue if the membership backings have
+  /// different capacities, or if either queue's pending values do not fit in
+  /// the other's buffer.
+  pub fn swap_contents_with(
+    &mut self,
+    other: &mut TinySetQueue<'_, T, S>,
+  ) -> Result<(), &'static str> {
+    if self.in_queue.capacity() != other.in_queue.capacity() {
+      return Err("membership backings have different capacities");
+    }
+    if self.len > other.buf.len() || other.len > self.buf.len() {
+      return Err("pending values do not fit in the other queue's buffer");
+    }
+
+    for chunk in 0..chunk_count(self.in_queue.capacity()) {
+      let mine = self.in_queue.chunk64(chunk);
+      let mut diff = mine ^ other.in_queue.chunk64(chunk);
+      while diff != 0 {
+        let bit = diff.trailing_zeros();
+        let idx = (chunk << 6) + bit as usize;
+        if mine & (1 << bit) != 0 {
+          self.unmark(idx);
+          other.mark(idx);
+        } else {
+          self.mark(idx);
+          other.unmark(idx);
+        }
+        diff &= diff - 1;
+      }
+    }
+
+    self.make_contiguous();
+    other.make_contiguous();
+    let shared = self.len.min(other.len);
+    self.buf[..shared].swap_with_slice(&mut other.buf[..shared]);
+    if self.len > shared {
+      other.buf[shared..self.len].copy_from_slice(&self.buf[shared..self.len]);
+    } else {
+      self.buf[shared..other.len]
+        .copy_from_slice(&other.buf[shared..other.len]);
+    }
+
+    core::mem::swap(&mut self.len, &mut other.len);
+    self.sync_leak_check();
+    other.sync_leak_check();
+    self.tail = if self.buf.is_empty() {
+      0
+    } else {
+      self.len % self.buf.len()
+    };
+    other.tail = if other.buf.is_empty() {
+      0
+    } else {
+      other.len % other.buf.len()
+    };
+    #[cfg(feature = "audit")]
+    core::mem::swap(&mut self.audit, &mut other.audit);
+    Ok(())
+  }
+
+  /// Writes the indices of the pending values into `out`, sorted ascending, and
+  /// returns how many were written.
+  ///
+  /// The queue is not modified. If `out` is shorter than `len()`, only the first
+  /// `out.len()` values in processing order are captured (and then sorted). The
+  /// sort is in place and allocation-free.
+  pub fn peek_sorted_indices(&self, out: &mut [usize]) -> usize {
+    let mut count = 0;
+    for (slot, value) in out.iter_mut().zip(self.iter()) {
+      *slot = (*value).into();
+      count += 1;
+    }
+    out[..count].sort_unstable();
+    count
+  }
+
+  /// Returns `true` if every value in `values` maps below
+  /// [`membership_capacity`](Self::membership_capacity).
+  ///
+  /// Use [`first_out_of_range`](Self::first_out_of_range) to learn which value
+  /// failed.
+  pub fn all_in_range(&self, values: &[T]) -> bool {
+    self.first_out_of_range(values).is_none()
+  }
+
+  /// Returns the position and value of the first element of `values` whose
+  /// index is not below [`membership_capacity`](Self::membership_capacity), or
+  /// `None` if every value can be pushed without an out-of-range error.
+  ///
+  /// Nothing is mutated, so a batch of external keys can be validated before
+  /// any of them are enqueued.
+  pub fn first_out_of_range(&self, values: &[T]) -> Option<(usize, T)> {
+    let capacity = self.in_queue.capacity();
+    values
+      .iter()
+      .enumerate()
+      .find(|(_, value)| (**value).into() >= capacity)
+      .map(|(position, value)| (position, *value))
+  }
+
+  /// Pops the next value and every following value whose index continues an
+  /// ascending run (`k`, `k + 1`, `k + 2`, ...), returning `(k, run_len)`.
+  ///
+  /// The run is measured in processing order: it ends at the first pending value
+  /// whose index is not exactly one greater than the previous one, and that
+  /// value stays queued. Keys that are consecutive but not adjacent in
+  /// processing order are not coalesced. Membership is handled per mode, as in
+  /// [`pop`](Self::pop). Returns `None` when the queue is empty.
+  pub fn pop_contiguous_run(&mut self) -> Option<(usize, usize)> {
+    let start: usize = self.pop()?.into();
+    let mut run_len = 1;
+    while let Some(&next) = self.peek() {
+      if next.into() != start + run_len {
+        break;
+      }
+      self.pop();
+      run_len += 1;
+    }
+    Some((start, run_len))
+  }
+
+  /// Pushes `value`, or moves an already-pending entry with the same index to
+  /// the front of processing order so it is popped next.
+  ///
+  /// Returns [`PushResult::Refreshed`] when a pending entry was moved; the
+  /// queued copy is replaced by `value`. Returns
+  /// [`PushResult::AlreadyPresent`] when the entry is already next in line or
+  /// when the index is marked but no longer pending
+  /// ([`MembershipMode::Visited`]). Otherwise behaves like [`push`](Self::push).
+  /// Finding the pending entry is a linear scan.
+  pub fn push_or_promote(
+    &mut self,
+    value: T,
+  ) -> Result<PushResult, PushError<T>> {
+    let index: usize = value.into();
+    if index >= self.in_queue.capacity() || !self.in_queue.contains(index) {
+      return self.push_indexed(index, value);
+    }
+
+    if self.order == ProcessingOrder::InsertionMin {
+      return Ok(PushResult::AlreadyPresent);
+    }
+    let position = match self.iter().position(|v| (*v).into() == index) {
+      Some(0) | None => return Ok(PushResult::AlreadyPresent),
+      Some(position) => position,
+    };
+
+    let cap = self.buf.len();
+    match self.order {
+      ProcessingOrder::Fifo => {
+        for offset in (0..position).rev() {
+          self.buf[(self.head + offset + 1) % cap] =
+            self.buf[(self.head + offset) % cap];
+        }
+        self.buf[self.head] = value;
+      }
+      ProcessingOrder::Lifo | ProcessingOrder::InsertionMin => {
+        let newest = self.len - 1;
+        for offset in newest - position..newest {
+          self.buf[(self.head + offset) % cap] =
+            self.buf[(self.head + offset + 1) % cap];
+        }
+        self.buf[(self.head + newest) % cap] = value;
+      }
+    }
+
+    Ok(PushResult::Refreshed)
+  }
+
+  /// Pushes `value`, or overwrites the pending entry with the same index so
+  /// the buffer holds the freshest copy.
+  ///
+  /// Useful when `value` carries data beyond its key, such as a generation
+  /// tag. Returns [`PushResult::Replaced`] when a pending entry was
+  /// overwritten; it keeps its place in processing order. Returns
+  /// [`PushResult::AlreadyPresent`] when the index is marked but no longer
+  /// pending ([`MembershipMode::Visited`]). Otherwise behaves like
+  /// [`push`](Self::push). Finding the pending entry is an O(len) scan.
+  ///
+  /// # Errors
+  ///
+  /// Returns a [`PushError`] under the same conditions as [`push`](Self::push).
+  pub fn push_or_replace(
+    &mut self,
+    value: T,
+  ) -> Result<PushResult, PushError<T>> {
+    let index: usize = value.into();
+    if index >= self.in_queue.capacity() || !self.in_queue.contains(index) {
+      return self.push_indexed(index, value);
+    }
+
+    let cap = self.buf.len();
+    let slot = (0..self.len)
+      .map(|offset| (self.head + offset) % cap)
+      .find(|&slot| self.buf[slot].into() == index);
+    match slot {
+      Some(slot) => {
+        self.buf[slot] = value;
+        Ok(PushResult::Replaced)
+      }
+      None => Ok(PushResult::AlreadyPresent),
+    }
+  }
+
+  /// Pops the next value and reports whether the queue is now empty.
+  ///
+  /// The flag is `true` exactly when this pop removed the last pending value,
+  /// which lets drain loops run finalization once without a separate
+  /// [`is_empty`](Self::is_empty) check.
+  pub fn pop_last_aware(&mut self) -> Option<(T, bool)> {
+    self.pop().map(|value| (value, self.len == 0))
+  }
+
+  /// Pops values in processing order and deals them round-robin into `dests`,
+  /// returning how many were inserted into a destination.
+  ///
+  /// Each popped value is offered to the next destination in turn. A
+  /// destination that is full or cannot represent the index is skipped and the
+  /// value is offered to the following one. A destination that reports
+  /// [`PushResult::AlreadyPresent`] absorbs the value as a duplicate: it is
+  /// consumed but not counted. Distribution stops when `self` is empty or
+  /// every destination is full; a value that no destination accepts is put
+  /// back at the front of `self`.
+  pub fn distribute_into(
+    &mut self,
+    dests: &mut [&mut TinySetQueue<'_, T, S>],
+  ) -> usize {
+    let mut distributed = 0;
+    let mut next = 0;
+    while !dests.iter().all(|dest| dest.is_full()) {
+      if self.is_empty() {
+        break;
+      }
+      let offset = self.next_offset();
+      let Some(value) = self.pop() else {
+        break;
+      };
+      let mut placed = false;
+      for step in 0..dests.len() {
+        let dest = (next + step) % dests.len();
+        if let Ok(result) = dests[dest].push(value) {
+          if result == PushResult::Inserted {
+            distributed += 1;
+          }
+          next = dest + 1;
+          placed = true;
+          break;
+        }
+      }
+      if !placed {
+        self.unpop(value, offset);
+        break;
+      }
+    }
+    distributed
+  }
+
+  /// Keeps only the pending values for which `keep` returns `true`.
+  ///
+  /// `keep` is called once per pending value, oldest to newest. Removed values
+  /// leave the queue without being processed, so their membership is cleared
+  /// in both modes, as with [`remove`](Self::remove), and they can be pushed
+  /// again. Kept values stay in their relative order.
+  ///
+  /// The ring is compacted in a single pass with a read and a write cursor, so
+  /// the whole call is O(len) regardless of how many values are removed.
+  pub fn retain<F>(&mut self, mut keep: F)
+  where
+    F: FnMut(&T) -> bool,
+  {
+    let cap = self.buf.len();
+    let mut kept = 0;
+    for offset in 0..self.len {
+      let value = self.buf[(self.head + offset) % cap];
+      if keep(&value) {
+        self.buf[(self.head + kept) % cap] = value;
+        kept += 1;
+      } else {
+        self.unmark(value.into());
+      }
+    }
+    self.len = kept;
+    self.tail = if cap == 0 {
+      0
+    } else {
+      (self.head + kept) % cap
+    };
+    self.sync_leak_check();
+  }
+
+  /// Consumes the queue and returns its buffer and membership backing.
+  ///
+  /// Contents are left as-is; use [`into_parts_full`](Self::into_parts_full)
+  /// to also recover the ring state.
+  pub fn into_parts(self) -> (&'a mut [T], &'a mut S) {
+    let parts = self.into_parts_full();
+    (parts.buf, parts.in_queue)
+  }
+
+  /// Consumes the queue and returns its storage together with the ring
+  /// indices, mode and order.
+  ///
+  /// Passing the result to [`from_parts_full`](Self::from_parts_full) rebuilds
+  /// a queue that pops the identical sequence.
+  pub fn into_parts_full(mut self) -> QueueParts<'a, T, S> {
+    // Handing the state to the caller is not a leak.
+    self.set_leak_check_enabled(false);
+    let (head, tail, len, mode, order) =
+      (self.head, self.tail, self.len, self.mode, self.order);
+    let TinySetQueue { buf, in_queue, .. } = self;
+    QueueParts {
+      buf,
+      in_queue,
+      head,
+      tail,
+      len,
+      mode,
+      order,
+    }
+  }
+
+  /// Rebuilds a queue from [`QueueParts`] without clearing membership.
+  ///
+  /// # Errors
+  ///
+  /// Returns a description of the first violated constraint when the indices
+  /// do not describe a valid window of `buf`, with the same rules as
+  /// [`set_indices`](Self::set_indices). Agreement between the buffer contents
+  /// and the membership backing is not checked.
+  pub fn from_parts_full(
+    parts: QueueParts<'a, T, S>,
+  ) -> Result<Self, &'static str> {
+    check_indices(parts.buf.len(), parts.head, parts.tail, parts.len)?;
+    let mut queue = Self::from_raw(
+      parts.buf,
+      parts.in_queue,
+      parts.mode,
+      parts.order,
+      (parts.head, parts.tail, parts.len),
+    );
+    queue.set_leak_check_enabled(true);
+    Ok(queue)
+  }
+
+  /// Returns the number of indices currently marked in the membership
+  /// backing.
+  ///
+  /// In [`MembershipMode::InQueue`] this equals [`len`](Self::len); in
+  /// [`MembershipMode::Visited`] it also counts indices already popped. The
+  /// count scans the whole backing unless the `members_cache` feature holds
+  /// a result from `members_count_cached` that no membership change has
+  /// invalidated since.
+  pub fn members_count(&self) -> usize {
+    #[cfg(feature = "members_cache")]
+    if !self.members_dirty {
+      return self.members_cached;
+    }
+    self.in_queue.count()
+  }
+
+  /// Like [`members_count`](Self::members_count), but stores the result so
+  /// repeated calls between membership changes are O(1). Enabled by the
+  /// `members_cache` feature.
+  #[cfg(feature = "members_cache")]
+  pub fn members_count_cached(&mut self) -> usize {
+    if self.members_dirty {
+      self.members_cached = self.in_queue.count();
+      self.members_dirty = false;
+    }
+    self.members_cached
+  }
+
+  /// Pushes `value`, reporting out-of-range and full as distinct outcomes.
+  ///
+  /// Unlike [`push`](Self::push), this returns a single [`PushOutcome`] that
+  /// can be matched exhaustively without the rejected value. A value that is
+  /// already present yields `Pushed(AlreadyPresent)` even when the queue is
+  /// full.
+  pub fn push_if_in_range(&mut self, value: T) -> PushOutcome {
+    let index: usize = value.into();
+    if index >= self.in_queue.capacity() {
+      return PushOutcome::OutOfRange;
+    }
+    match self.push_indexed(index, value) {
+      Ok(result) => PushOutcome::Pushed(result),
+      Err(_) => PushOutcome::Full,
+    }
+  }
+
+  /// Copies the queue's state into caller-provided storage without
+  /// allocating.
+  ///
+  /// The pending values are written to the front of `buf_out` in insertion
+  /// order, and `membership_out` is overwritten with the membership flags. The
+  /// returned [`CapturedState`] plus both buffers are enough for
+  /// [`restore`](Self::restore) to rebuild a queue that pops the same
+  /// sequence.
+  ///
+  /// # Errors
+  ///
+  /// Fails without writing anything if `buf_out` cannot hold the pending
+  /// values or `membership_out` is smaller than the membership backing.
+  pub fn capture(
+    &self,
+    buf_out: &mut [T],
+    membership_out: &mut S,
+  ) -> Result<CapturedState, CaptureError> {
+    if buf_out.len() < self.len {
+      return Err(CaptureError::BufferTooSmall);
+    }
+    if membership_out.capacity() < self.in_queue.capacity() {
+      return Err(CaptureError::MembershipTooSmall);
+    }
+
+    for (offset, slot) in buf_out[..self.len].iter_mut().enumerate() {
+      *slot = self.buf[(self.head + offset) % self.buf.len()];
+    }
+    membership_out.clear_all();
+    for_each_set_bit(&*self.in_queue, |index| membership_out.insert(index));
+
+    Ok(CapturedState {
+      len: self.len,
+      mode: self.mode,
+      order: self.order,
+    })
+  }
+
+  /// Replaces the queue's contents, mode and order with a state recorded by
+  /// [`capture`](Self::capture).
+  ///
+  /// # Errors
+  ///
+  /// Fails without modifying the queue if `buf_in` holds fewer than
+  /// `captured.len` values, the pending values do not fit in this queue, or
+  /// `membership_in` is larger than this queue's membership backing.
+  pub fn restore(
+    &mut self,
+    captured: &CapturedState,
+    buf_in: &[T],
+    membership_in: &S,
+  ) -> Result<(), CaptureError> {
+    if buf_in.len() < captured.len || self.buf.len() < captured.len {
+      return Err(CaptureError::BufferTooSmall);
+    }
+    if membership_in.capacity() > self.in_queue.capacity() {
+      return Err(CaptureError::MembershipTooSmall);
+    }
+
+    self.clear();
+    for_each_set_bit(membership_in, |index| self.mark(index));
+    self.buf[..captured.len].copy_from_slice(&buf_in[..captured.len]);
+    self.len = captured.len;
+    self.tail = captured.len % self.buf.len().max(1);
+    self.mode = captured.mode;
+    self.order = captured.order;
+    self.sync_leak_check();
+    Ok(())
+  }
+
+  /// Returns the fraction of the membership domain currently marked, from
+  /// `0.0` to `1.0`.
+  ///
+  /// A low density suggests a `[u64]` bitset backing is the more
+  /// memory-efficient choice; a consistently high one may favor `[bool]` for
+  /// simpler access. An empty domain yields `0.0` rather than NaN.
+  pub fn members_density(&self) -> f32 {
+    let capacity = self.in_queue.capacity();
+    if capacity == 0 {
+      return 0.0;
+    }
+    self.members_count() as f32 / capacity as f32
+  }
+
+  /// Writes the number of marked indices in each 64-index word of the
+  /// membership domain into `out`, and returns how many words were written.
+  ///
+  /// Entry `i` covers indices `64 * i..64 * (i + 1)`, matching the words of a
+  /// `[u64]` backing; other backings are grouped the same way. Only the first
+  /// `out.len()` words are reported when `out` is shorter than the domain.
+  pub fn members_word_counts(&self, out: &mut [u8]) -> usize {
+    let words = chunk_count(self.in_queue.capacity()).min(out.len());
+    for (chunk, slot) in out[..words].iter_mut().enumerate() {
+      *slot = self.in_queue.chunk64(chunk).count_ones() as u8;
+    }
+    words
+  }
+
+  /// Pops every pending value in processing order and hands it to `sink`
+  /// together with the bucket index chosen by `class_of`.
+  ///
+  /// Membership is handled per mode, as in [`pop`](Self::pop). The queue is
+  /// empty afterwards unless `sink` panics.
+  pub fn drain_grouped<C, F>(&mut self, class_of: C, mut sink: F)
+  where
+    C: Fn(&T) -> usize,
+    F: FnMut(usize, T),
+  {
+    while let Some(value) = self.pop() {
+      sink(class_of(&value), value);
+    }
+  }
+
+  /// Returns one past the highest membership index inserted since the last
+  /// [`clear`](Self::clear), or `0` if nothing was inserted.
+  ///
+  /// `clear` only zeros the backing below this bound. Queues whose backing
+  /// may hold flags the queue did not set (built without `clear_on_new`, or
+  /// rebuilt from parts) start at the full membership capacity.
+  #[inline]
+  pub fn high_water(&self) -> usize {
+    self.high_water
+  }
+
+  /// Empties the queue and clears only the touched part of the membership
+  /// backing, below [`high_water`](Self::high_water).
+  ///
+  /// The queue already tracks one past the largest index it inserted, so this
+  /// is the same work [`clear`](Self::clear) does; the name documents the
+  /// intent at call sites that size the backing for a sparse worst case.
+  #[inline]
+  pub fn clear_touched(&mut self) {
+    self.clear();
+  }
+
+  /// Resets the [`high_water`](Self::high_water) bound to `0`.
+  ///
+  /// Use this when the membership backing is known to be clear below the
+  /// current bound, e.g. after resetting it outside the queue. If any flag is
+  /// still set, later calls to [`clear`](Self::clear) will not reset it.
+  #[inline]
+  pub fn reset_high_water(&mut self) {
+    self.high_water = 0;
+  }
+
+  /// Removes the pending value whose key is `index` by moving the newest
+  /// pending value into its slot, like [`Vec::swap_remove`].
+  ///
+  /// **This breaks processing order**: the value that was last in insertion
+  /// order takes the removed value's place. In exchange no other values are
+  /// shifted. The removed key was never processed, so its membership flag is
+  /// cleared in both modes and it can be pushed again. Returns `None` when no
+  /// pending value has that key. Locating the value is a scan of the pending
+  /// values, skipped when the key is not marked.
+  ///
+  /// [`Vec::swap_remove`]: https://doc.rust-lang.org/std/vec/struct.Vec.html#method.swap_remove
+  pub fn swap_remove_key(&mut self, index: usize) -> Option<T> {
+    if index >= self.in_queue.capacity() || !self.in_queue.contains(index) {
+      return None;
+    }
+    let cap = self.buf.len();
+    let offset = (0..self.len)
+      .find(|offset| self.buf[(self.head + offset) % cap].into() == index)?;
+
+    let slot = (self.head + offset) % cap;
+    let value = self.buf[slot];
+    self.tail = (self.tail + cap - 1) % cap;
+    self.buf[slot] = self.buf[self.tail];
+    self.unmark(index);
+    self.len -= 1;
+    self.sync_leak_check();
+    Some(value)
+  }
+
+  /// Removes the pending `value` while preserving the order of the others,
+  /// and returns whether it was pending.
+  ///
+  /// Every value pushed after it is shifted back by one slot to close the
+  /// gap, so removal is O(len); [`swap_remove_key`](Self::swap_remove_key)
+  /// avoids the shift at the cost of reordering. As there, the membership flag
+  /// is cleared in both modes since the value was never processed.
+  pub fn remove(&mut self, value: T) -> bool {
+    let index: usize = value.into();
+    if index >= self.in_queue.capacity() || !self.in_queue.contains(index) {
+      return false;
+    }
+    let cap = self.buf.len();
+    let Some(offset) = (0..self.len)
+      .find(|offset| self.buf[(self.head + offset) % cap].into() == index)
+    else {
+      return false;
+    };
+
+    remove_at_offset(self.buf, self.head, self.len, offset);
+    self.tail = (self.tail + cap - 1) % cap;
+    self.unmark(index);
+    self.len -= 1;
+    self.sync_leak_check();
+    true
+  }
+
+  /// Returns a parallel iterator over every index marked in the membership
+  /// backing, for post-processing very large visited sets on multicore hosts.
+  ///
+  /// The backing is split into 64-index words that are scanned in parallel, so
+  /// indices are not yielded in ascending order. Enabled by the `rayon`
+  /// feature.
+  #[cfg(feature = "rayon")]
+  pub fn par_members(
+    &self,
+  ) -> impl rayon::iter::ParallelIterator<Item = usize> + '_
+  where
+    S: Sync,
+  {
+    use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+    let backing: &S = self.in_queue;
+    (0..chunk_count(backing.capacity()))
+      .into_par_iter()
+      .flat_map_iter(move |chunk| {
+        let base = chunk << 6;
+        core::iter::successors(Some(backing.chunk64(chunk)), |bits| {
+          Some(bits & bits.wrapping_sub(1))
+        })
+        .take_while(|&bits| bits != 0)
+        .map(move |bits| base + bits.trailing_zeros() as usize)
+      })
+  }
+
+  /// Discards leading values while `is_stale` returns `true`, returning how
+  /// many were dropped.
+  ///
+  /// Values are taken in processing order and membership is handled per mode,
+  /// as in [`pop`](Self::pop). The first value `is_stale` rejects stays at the
+  /// front. The cost is proportional to the number of values trimmed, not to
+  /// the queue length.
+  pub fn trim_front<F>(&mut self, mut is_stale: F) -> usize
+  where
+    F: FnMut(&T) -> bool,
+  {
+    let mut trimmed = 0;
+    while self.pop_if(&mut is_stale).is_some() {
+      trimmed += 1;
+    }
+    trimmed
+  }
+
+  /// Pushes `value` and reports the physical ring slot that holds it.
+  ///
+  /// For [`PushResult::Inserted`] the slot is where the value was just
+  /// written. For [`PushResult::AlreadyPresent`] it is the slot of the pending
+  /// value with the same key, found by scanning, or `None` when the key is
+  /// only marked as visited and no longer pending. Slots are reused as the
+  /// ring wraps, so side data keyed by slot is only valid until that value is
+  /// popped (or moved by methods that compact the ring, such as
+  /// [`retain`](Self::retain) or [`make_contiguous`](Self::make_contiguous)).
+  ///
+  /// # Errors
+  ///
+  /// Returns a [`PushError`] under the same conditions as [`push`](Self::push).
+  pub fn push_at(
+    &mut self,
+    value: T,
+  ) -> Result<(PushResult, Option<usize>), PushError<T>> {
+    let slot = self.tail;
+    match self.push(value)? {
+      PushResult::Inserted => Ok((PushResult::Inserted, Some(slot))),
+      result => {
+        let index: usize = value.into();
+        let cap = self.buf.len();
+        let slot = (0..self.len)
+          .map(|offset| (self.head + offset) % cap)
+          .find(|&slot| self.buf[slot].into() == index);
+        Ok((result, slot))
+      }
+    }
+  }
+
+  /// Rebuilds a queue from [`QueueParts`] after checking that the membership
+  /// backing agrees with the pending values.
+  ///
+  /// On top of the index checks of [`from_parts_full`](Self::from_parts_full),
+  /// every pending value must map to a distinct marked index inside the
+  /// backing, and in [`MembershipMode::InQueue`] no index may be marked
+  /// without a pending value. The extra checks cost O(len) plus a scan of the
+  /// backing, so prefer `from_parts_full` for state that is already trusted.
+  ///
+  /// # Errors
+  ///
+  /// Returns a [`StateError`] describing the first inconsistency found.
+  pub fn from_parts_validated(
+    parts: QueueParts<'a, T, S>,
+  ) -> Result<Self, StateError> {
+    check_state(
+      parts.buf,
+      &mut *parts.in_queue,
+      parts.mode,
+      (parts.head, parts.tail, parts.len),
+    )?;
+    Self::from_parts_full(parts).map_err(StateError::Indices)
+  }
+
+  /// Resumes a queue from stored ring state without re-pushing its values.
+  ///
+  /// Shorthand for [`from_parts_validated`](Self::from_parts_validated) with
+  /// the fields passed individually. The backing is never cleared, whatever
+  /// the `clear_on_new` feature says, since it is part of the restored state.
+  ///
+  /// # Errors
+  ///
+  /// Returns a [`StateError`] describing the first inconsistency found.
+  pub fn from_parts(
+    buf: &'a mut [T],
+    in_queue: &'a mut S,
+    mode: MembershipMode,
+    order: ProcessingOrder,
+    head: usize,
+    tail: usize,
+    len: usize,
+  ) -> Result<Self, StateError> {
+    Self::from_parts_validated(QueueParts {
+      buf,
+      in_queue,
+      head,
+      tail,
+      len,
+      mode,
+      order,
+    })
+  }
+
+  /// Pushes `value` unless its index is marked in `exclude`.
+  ///
+  /// Excluded indices yield [`PushResult::Excluded`] without touching the
+  /// queue or its membership, which keeps a fixed set of forbidden keys
+  /// separate from visited/in-queue tracking. Indices beyond `exclude`'s
+  /// capacity are treated as not excluded. Otherwise behaves like
+  /// [`push`](Self::push).
+  pub fn push_unless_excluded<E>(
+    &mut self,
+    value: T,
+    exclude: &E,
+  ) -> Result<PushResult, PushError<T>>
+  where
+    E: SetBacking + ?Sized,
+  {
+    let index: usize = value.into();
+    if index < exclude.capacity() && exclude.contains(index) {
+      return Ok(PushResult::Excluded);
+    }
+    self.push_indexed(index, value)
+  }
+
+  /// Writes the marked membership indices as `(start, length)` runs of
+  /// consecutive indices, in ascending order, and returns the total number of
+  /// runs.
+  ///
+  /// Only the first `out.len()` runs are written. A return value larger than
+  /// `out.len()` means the output was truncated and tells the caller how much
+  /// room a complete encoding needs. Clustered visited sets encode far more
+  /// compactly than a raw bitmap; fully set words are consumed 64 indices at a
+  /// time.
+  pub fn members_rle(&self, out: &mut [(usize, usize)]) -> usize {
+    let mut runs = 0;
+    let mut current: Option<(usize, usize)> = None;
+    let mut emit = |run: (usize, usize)| {
+      if let Some(slot) = out.get_mut(runs) {
+        *slot = run;
+      }
+      runs += 1;
+    };
+
+    for chunk in 0..chunk_count(self.in_queue.capacity()) {
+      let base = chunk << 6;
+      let mut bits = self.in_queue.chunk64(chunk);
+      if bits == u64::MAX {
+        match &mut current {
+          Some((start, len)) if *start + *len == base => *len += 64,
+          _ => {
+            if let Some(run) = current.take() {
+              emit(run);
+            }
+            current = Some((base, 64));
+          }
+        }
+        continue;
+      }
+      while bits != 0 {
+        let index = base + bits.trailing_zeros() as usize;
+        match &mut current {
+          Some((start, len)) if *start + *len == index => *len += 1,
+          _ => {
+            if let Some(run) = current.take() {
+              emit(run);
+            }
+            current = Some((index, 1));
+          }
+        }
+        bits &= bits - 1;
+      }
+    }
+    if let Some(run) = current {
+      emit(run);
+    }
+    runs
+  }
+
+  /// Marks every index covered by the `(start, length)` runs, as produced by
+  /// [`members_rle`](Self::members_rle).
+  ///
+  /// No values are enqueued, so this is meant for restoring a
+  /// [`MembershipMode::Visited`] set; in [`MembershipMode::InQueue`] the
+  /// marked indices would have no pending values. Existing flags are kept.
+  ///
+  /// # Panics
+  ///
+  /// Panics if a run extends past [`membership_capacity`](Self::membership_capacity).
+  pub fn set_members_from_rle(&mut self, runs: &[(usize, usize)]) {
+    for &(start, len) in runs {
+      assert!(
+        start + len <= self.in_queue.capacity(),
+        "run exceeds the membership backing"
+      );
+      for index in start..start + len {
+        self.mark(index);
+      }
+    }
+  }
+
+  /// Returns how many distinct indices have been visited: the number of flags
+  /// set in the membership backing.
+  ///
+  /// Meant for [`MembershipMode::Visited`], where flags persist after popping;
+  /// in [`MembershipMode::InQueue`] it equals [`len`](Self::len). The same
+  /// count as [`members_count`](Self::members_count), under the name that
+  /// reads well in `Visited` mode, and served from the same cache.
+  #[inline]
+  pub fn visited_count(&self) -> usize {
+    self.members_count()
+  }
+
+  /// Returns the key of the value stored in ring `slot` if that slot currently
+  /// holds a pending value, or `None` for free or out-of-bounds slots.
+  ///
+  /// Pairs with [`push_at`](Self::push_at): side tables keyed by slot can check
+  /// that a slot still belongs to the value they expect. A slot is pending when
+  /// its distance from `head`, wrapping around the end of the ring, is less
+  /// than [`len`](Self::len).
+  pub fn key_at_slot(&self, slot: usize) -> Option<usize> {
+    let cap = self.buf.len();
+    if slot >= cap || (slot + cap - self.head) % cap >= self.len {
+      return None;
+    }
+    Some(self.buf[slot].into())
+  }
+
+  /// Pops at most `budget` values in processing order, passing each to `f`,
+  /// and returns how many were processed.
+  ///
+  /// Membership is handled per mode, as in [`pop`](Self::pop). Values beyond
+  /// the budget stay queued in their original order, ready for the next slice
+  /// of work.
+  pub fn drain_budget<F>(&mut self, budget: usize, mut f: F) -> usize
+  where
+    F: FnMut(T),
+  {
+    let mut processed = 0;
+    while processed < budget {
+      let Some(value) = self.pop() else {
+        break;
+      };
+      f(value);
+      processed += 1;
+    }
+    processed
+  }
+
+  /// Pushes `values` in order until one is rejected, returning how many were
+  /// handled.
+  ///
+  /// Values that are already present count as handled without consuming a
+  /// slot. The first value that fails because the queue is full or its index
+  /// is out of range stops the batch, so `values[count..]` is exactly the
+  /// remainder to retry later.
+  pub fn try_push_all(&mut self, values: &[T]) -> usize {
+    values
+      .iter()
+      .take_while(|&&value| self.push(value).is_ok())
+      .count()
+  }
+
+  /// Makes sure `value` is either queued or already handled, reporting which.
+  ///
+  /// Splits the [`PushResult::AlreadyPresent`] case of [`push`](Self::push)
+  /// into a pending value ([`EnsureResult::AlreadyQueued`]) and one that was
+  /// already popped in [`MembershipMode::Visited`]
+  /// ([`EnsureResult::AlreadyVisited`]). Telling them apart in `Visited` mode
+  /// scans the pending values; in [`MembershipMode::InQueue`] a marked index
+  /// is always pending.
+  ///
+  /// # Errors
+  ///
+  /// Returns a [`PushError`] under the same conditions as [`push`](Self::push).
+  pub fn ensure_queued(
+    &mut self,
+    value: T,
+  ) -> Result<EnsureResult, PushError<T>> {
+    let index: usize = value.into();
+    if index < self.in_queue.capacity() && self.in_queue.contains(index) {
+      let pending = self.mode == MembershipMode::InQueue
+        || self.iter().any(|queued| (*queued).into() == index);
+      return Ok(if pending {
+        EnsureResult::AlreadyQueued
+      } else {
+        EnsureResult::AlreadyVisited
+      });
+    }
+    self
+      .push_indexed(index, value)
+      .map(|_| EnsureResult::NewlyQueued)
+  }
+
+  /// Binary-searches the pending values for the first position, counted from
+  /// the oldest value, whose key does not satisfy `pred`.
+  ///
+  /// Mirrors [`slice::partition_point`]: keys are `value.into()` and positions
+  /// follow insertion order regardless of [`ProcessingOrder`], wrapping
+  /// around the end of the ring. The pending values must be partitioned by
+  /// `pred`, for example by pushing keys in ascending order and searching with
+  /// `|key| key < target`; otherwise the result is unspecified. Runs in
+  /// O(log len).
+  pub fn partition_point_by_key(&self, pred: impl Fn(usize) -> bool) -> usize {
+    let (mut low, mut high) = (0, self.len);
+    while low < high {
+      let mid = low + (high - low) / 2;
+      if pred(self.buf[(self.head + mid) % self.buf.len()].into()) {
+        low = mid + 1;
+      } else {
+        high = mid;
+      }
+    }
+    low
+  }
+
+  /// Returns an iterator that pops every pending value in processing order.
+  ///
+  /// Each step goes through [`pop`](Self::pop), so membership is cleared or
+  /// kept according to the [`MembershipMode`]. Dropping the iterator early
+  /// pops the rest, leaving the queue empty either way.
+  pub fn drain(&mut self) -> Drain<'_, 'a, T, S> {
+    Drain { queue: self }
+  }
+
+  /// Switches the processing order, keeping any pending values.
+  ///
+  /// The ring always stores pending values in insertion order between `head`
+  /// and `tail`, so only the end that `pop` takes from changes: after
+  /// switching from LIFO to FIFO the oldest pending value comes out next, and
+  /// the reverse for FIFO to LIFO. `len` and the wrap-around indices are
+  /// untouched, so this is safe to call at any time.
+  #[inline]
+  pub fn set_order(&mut self, order: ProcessingOrder) {
+    self.order = order;
+  }
+
+  /// Switches the membership mode, keeping any pending values and flags.
+  ///
+  /// Only later pops are affected: under [`MembershipMode::InQueue`] they
+  /// clear the popped value's flag, under [`MembershipMode::Visited`] they
+  /// keep it. Nothing is rescanned, so switching from `Visited` to `InQueue`
+  /// while values are pending is meant to make those values re-enqueueable
+  /// once popped, whereas indices popped before the switch stay marked until
+  /// the next [`clear`](Self::clear).
+  #[inline]
+  pub fn set_mode(&mut self, mode: MembershipMode) {
+    self.mode = mode;
+    self.sync_leak_check();
+  }
+
+  /// Copies the pending values and membership flags into an owned
+  /// [`QueueState`] for checkpointing. Enabled by the `serde` feature.
+  #[cfg(feature = "serde")]
+  pub fn snapshot(&self) -> QueueState<T> {
+    let cap = self.buf.len();
+    let pending = (0..self.len)
+      .map(|offset| self.buf[(self.head + offset) % cap])
+      .collect();
+    let mut members = alloc::vec::Vec::new();
+    for_each_set_bit(&*self.in_queue, |index| members.push(index));
+    QueueState {
+      pending,
+      members,
+      mode: self.mode,
+      order: self.order,
+    }
+  }
+
+  /// Rebuilds a queue from a [`QueueState`] in caller-supplied storage.
+  ///
+  /// The backing is cleared first, the recorded indices are marked, and the
+  /// pending values are pushed again in insertion order, so the queue pops the
+  /// same sequence and rejects the same values as the one that was
+  /// snapshotted. Enabled by the `serde` feature.
+  ///
+  /// The state usually comes from a deserializer, so it is checked rather than
+  /// trusted: every pending value must be distinct and recorded in
+  /// `state.members`, and in [`MembershipMode::InQueue`] the members must be
+  /// exactly the pending values.
+  ///
+  /// # Errors
+  ///
+  /// Returns [`CaptureError::BufferTooSmall`] if `buf` cannot hold the pending
+  /// values, [`CaptureError::MembershipTooSmall`] if any recorded index is
+  /// beyond `in_queue`, or [`CaptureError::InconsistentState`] if the pending
+  /// values and members disagree. After an `InconsistentState` error
+  /// `in_queue` may hold some of the recorded indices.
+  #[cfg(feature = "serde")]
+  pub fn from_snapshot(
+    buf: &'a mut [T],
+    in_queue: &'a mut S,
+    state: &QueueState<T>,
+  ) -> Result<Self, CaptureError> {
+    if buf.len() < state.pending.len() {
+      return Err(CaptureError::BufferTooSmall);
+    }
+    let capacity = in_queue.capacity();
+    let in_range = |index: usize| index < capacity;
+    if !state.members.iter().copied().all(in_range)
+      || !state.pending.iter().all(|&value| in_range(value.into()))
+    {
+      return Err(CaptureError::MembershipTooSmall);
+    }
+
+    let mut queue = Self::new_with_config(
+      buf,
+      in_queue,
+      QueueConfig {
+        mode: state.mode,
+        order: state.order,
+        clear_on_new: true,
+      },
+    );
+    for &index in &state.members {
+      queue.mark(index);
+    }
+    // Unmark each pending index as it is matched, so a duplicate or a value
+    // missing from `members` finds its flag already clear.
+    for &value in &state.pending {
+      let index: usize = value.into();
+      if !queue.in_queue.contains(index) {
+        return Err(CaptureError::InconsistentState);
+      }
+      queue.unmark(index);
+    }
+    if state.mode == MembershipMode::InQueue && queue.in_queue.count() != 0 {
+      return Err(CaptureError::InconsistentState);
+    }
+    for &value in &state.pending {
+      // Fits, is in range and is unmarked, checked above.
+      let pushed = queue.push(value);
+      debug_assert!(matches!(pushed, Ok(PushResult::Inserted)));
+    }
+    Ok(queue)
+  }
+
+  /// Drops the value the next [`pop`](Self::pop) would yield without
+  /// processing it, and returns it for logging.
+  ///
+  /// Behaves exactly like `pop`, including membership: the flag is cleared in
+  /// [`MembershipMode::InQueue`] and kept in [`MembershipMode::Visited`]. The
+  /// separate name marks call sites that skip work rather than do it.
+  #[inline]
+  pub fn discard_next(&mut self) -> Option<T> {
+    self.pop()
+  }
+
+  /// Reopens a value popped in [`MembershipMode::Visited`] by clearing its
+  /// flag, so it can be pushed again, and returns whether it did.
+  ///
+  /// Meant for selectively revisiting nodes, e.g. in iterative deepening,
+  /// without clearing the whole visited set. Returns `false` without changes
+  /// in [`MembershipMode::InQueue`], when the index is out of range or not
+  /// marked, or when the value is still pending: clearing its flag then would
+  /// let a second copy be queued. The pending check scans the queue, O(len).
+  pub fn mark_unvisited(&mut self, value: T) -> bool {
+    let index: usize = value.into();
+    if self.mode != MembershipMode::Visited
+      || index >= self.in_queue.capacity()
+      || !self.in_queue.contains(index)
+      || self.iter().any(|&pending| pending.into() == index)
+    {
+      return false;
+    }
+    self.unmark(index);
+    true
+  }
+}
+
+impl<'a, T, S> private::SealedView for TinySetQueue<'a, T, S> where
+  S: SetBacking + ?Sized
+{
+}
+
+impl<'a, T, S> QueueView<T> for TinySetQueue<'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  fn len(&self) -> usize {
+    self.len
+  }
+
+  fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  fn is_full(&self) -> bool {
+    self.len == self.buf.len()
+  }
+
+  fn capacity(&self) -> usize {
+    self.buf.len()
+  }
+
+  fn peek(&self) -> Option<&T> {
+    TinySetQueue::peek(self)
+  }
+
+  fn contains(&self, value: T) -> bool {
+    TinySetQueue::contains(self, value)
+  }
+
+  fn iter(&self) -> Iter<'_, T> {
+    Iter::new(self.buf, self.head, self.len, self.order)
+  }
+}
+
+/// Pushes each item in turn, stopping at the first one that is rejected.
+///
+/// `extend` cannot report failure, so the rejected item and everything after
+/// it are dropped once the queue is full or an index is out of range. Use
+/// [`TinySetQueue::try_push_all`] when the caller needs to know where the
+/// batch stopped.
+impl<'a, T, S> Extend<T> for TinySetQueue<'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+    for value in iter {
+      if self.push(value).is_err() {
+        break;
+      }
+    }
+  }
+}
+
+/// Drains the queue, so `for value in &mut queue` pops every pending value.
+///
+/// Same as [`TinySetQueue::drain`]: membership follows the
+/// [`MembershipMode`], and breaking out of the loop still empties the queue.
+impl<'q, 'a, T, S> IntoIterator for &'q mut TinySetQueue<'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  type Item = T;
+  type IntoIter = Drain<'q, 'a, T, S>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.drain()
+  }
+}
+
+/// A heap-allocated queue that owns its ring buffer and bitset membership.
+///
+/// Available with the `alloc` feature. Operations delegate to
+/// [`TinySetQueue`] over the owned storage, so behavior is identical; the
+/// owned form can be moved, stored in structs, or sent across threads.
+#[cfg(feature = "alloc")]
+pub struct OwnedTinySetQueue<T> {
+  buf: alloc::vec::Vec<T>,
+  in_queue: alloc::vec::Vec<u64>,
+  mode: MembershipMode,
+  order: ProcessingOrder,
+  head: usize,
+  tail: usize,
+  len: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<T> OwnedTinySetQueue<T>
+where
+  T: Copy + Into<usize>,
+{
+  /// Allocates a queue holding up to `capacity` pending values with a
+  /// membership domain of at least `domain` indices.
+  pub fn new(
+    capacity: usize,
+    domain: usize,
+    mode: MembershipMode,
+    order: ProcessingOrder,
+  ) -> Self
+  where
+    T: Default,
+  {
+    OwnedTinySetQueue {
+      buf: alloc::vec![T::default(); capacity],
+      in_queue: alloc::vec![0; chunk_count(domain)],
+      mode,
+      order,
+      head: 0,
+      tail: 0,
+      len: 0,
+    }
+  }
+
+  /// Runs `f` against a borrowed queue over the owned storage, then records
+  /// the resulting ring indices.
+  fn with_queue<R>(
+    &mut self,
+    f: impl FnOnce(&mut TinySetQueue<'_, T, [u64]>) -> R,
+  ) -> R {
+    let (result, indices) = {
+      let mut queue = TinySetQueue::from_raw(
+        &mut self.buf,
+        &mut self.in_queue[..],
+        self.mode,
+        self.order,
+        (self.head, self.tail, self.len),
+      );
+      let result = f(&mut queue);
+      (result, (queue.head, queue.tail, queue.len))
+    };
+    (self.head, self.tail, self.len) = indices;
+    result
+  }
+
+  /// Clears the queue and its membership without freeing storage.
+  pub fn clear(&mut self) {
+    self.with_queue(|queue| queue.clear());
+  }
+
+  /// Returns the maximum number of pending items the queue can hold.
+  #[inline]
+  pub fn capacity(&self) -> usize {
+    self.buf.len()
+  }
+
+  /// Returns the number of indices the membership backing can represent.
+  #[inline]
+  pub fn membership_capacity(&self) -> usize {
+    self.in_queue.len() << 6
+  }
+
+  /// Returns the number of items currently enqueued.
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Returns `true` when the queue is empty.
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Returns `true` when the queue is at full capacity.
+  #[inline]
+  pub fn is_full(&self) -> bool {
+    self.len == self.buf.len()
+  }
+
+  /// Pushes a value into the queue unless it is already present.
+  ///
+  /// # Errors
+  ///
+  /// Returns a [`PushError`] under the same conditions as
+  /// [`TinySetQueue::push`].
+  pub fn push(&mut self, value: T) -> Result<PushResult, PushError<T>> {
+    self.with_queue(|queue| queue.push(value))
+  }
+
+  /// Pops the next value according to the configured processing order, if any.
+  pub fn pop(&mut self) -> Option<T> {
+    self.with_queue(|queue| queue.pop())
+  }
+
+  /// Returns an iterator over the pending values in processing order.
+  pub fn iter(&self) -> Iter<'_, T> {
+    Iter::new(&self.buf, self.head, self.len, self.order)
+  }
+}
+
+/// A fixed-size queue that owns its ring buffer and bitset membership inline.
+///
+/// Holds `CAP` pending values in a `[T; CAP]` and tracks membership for
+/// `64 * WORDS` indices in a `[u64; WORDS]`, so it needs no allocator and no
+/// separately borrowed storage. Operations delegate to [`TinySetQueue`] over
+/// the owned arrays, so behavior is identical.
+pub struct TinySetArrayQueue<T, const CAP: usize, const WORDS: usize> {
+  buf: [T; CAP],
+  in_queue: [u64; WORDS],
+  mode: MembershipMode,
+  order: ProcessingOrder,
+  head: usize,
+  tail: usize,
+  len: usize,
+}
+
+impl<T, const CAP: usize, const WORDS: usize> TinySetArrayQueue<T, CAP, WORDS>
+where
+  T: Copy + Into<usize>,
+{
+  /// Creates an empty queue with default-initialized buffer slots and cleared
+  /// membership.
+  pub fn new(mode: MembershipMode, order: ProcessingOrder) -> Self
+  where
+    T: Default,
+  {
+    TinySetArrayQueue {
+      buf: [T::default(); CAP],
+      in_queue: [0; WORDS],
+      mode,
+      order,
+      head: 0,
+      tail: 0,
+      len: 0,
+    }
+  }
+
+  /// Runs `f` against a borrowed queue over the owned storage, then records
+  /// the resulting ring indices.
+  fn with_queue<R>(
+    &mut self,
+    f: impl FnOnce(&mut TinySetQueue<'_, T, [u64]>) -> R,
+  ) -> R {
+    let (result, indices) = {
+      let mut queue = TinySetQueue::from_raw(
+        &mut self.buf,
+        &mut self.in_queue[..],
+        self.mode,
+        self.order,
+        (self.head, self.tail, self.len),
+      );
+      let result = f(&mut queue);
+      (result, (queue.head, queue.tail, queue.len))
+    };
+    (self.head, self.tail, self.len) = indices;
+    result
+  }
+
+  /// Clears the queue and its membership.
+  pub fn clear(&mut self) {
+    self.with_queue(|queue| queue.clear());
+  }
+
+  /// Returns the maximum number of pending items the queue can hold.
+  #[inline]
+  pub fn capacity(&self) -> usize {
+    CAP
+  }
+
+  /// Returns the number of indices the membership backing can represent.
+  #[inline]
+  pub fn membership_capacity(&self) -> usize {
+    WORDS << 6
+  }
+
+  /// Returns the number of items currently enqueued.
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Returns `true` when the queue is empty.
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Returns `true` when the queue is at full capacity.
+  #[inline]
+  pub fn is_full(&self) -> bool {
+    self.len == CAP
+  }
+
+  /// Pushes a value into the queue unless it is already present.
+  ///
+  /// # Errors
+  ///
+  /// Returns a [`PushError`] under the same conditions as
+  /// [`TinySetQueue::push`].
+  pub fn push(&mut self, value: T) -> Result<PushResult, PushError<T>> {
+    self.with_queue(|queue| queue.push(value))
+  }
+
+  /// Pops the next value according to the configured processing order, if any.
+  pub fn pop(&mut self) -> Option<T> {
+    self.with_queue(|queue| queue.pop())
+  }
+
+  /// Returns an iterator over the pending values in processing order.
+  pub fn iter(&self) -> Iter<'_, T> {
+    Iter::new(&self.buf, self.head, self.len, self.order)
+  }
+}
+
+/// A queue for values that are not `Copy`, such as structs holding a
+/// `String`.
+///
+/// Slots are `Option<T>` so `pop` can move a value out with
+/// [`Option::take`], and the membership index comes from `key(&value)`
+/// instead of consuming `value.into()`. Otherwise it follows
+/// [`TinySetQueue`]: the same membership modes, processing orders and
+/// direct-mapped backings.
+///
+/// ```
+/// use tinysetqueue::{KeyedTinySetQueue, MembershipMode, ProcessingOrder};
+///
+/// struct Job {
+///   id: usize,
+///   name: String,
+/// }
+///
+/// let mut buf: [Option<Job>; 4] = Default::default();
+/// let mut membership = [false; 16];
+/// let mut queue = KeyedTinySetQueue::new(
+///   &mut buf,
+///   &mut membership,
+///   |job: &Job| job.id,
+///   MembershipMode::InQueue,
+///   ProcessingOrder::Fifo,
+/// );
+/// queue.push(Job { id: 3, name: "build".into() }).ok().unwrap();
+/// assert_eq!(queue.pop().unwrap().name, "build");
+/// ```
+pub struct KeyedTinySetQueue<'a, T, S, F>
+where
+  S: SetBacking + ?Sized,
+{
+  buf: &'a mut [Option<T>],
+  in_queue: &'a mut S,
+  key: F,
+  mode: MembershipMode,
+  order: ProcessingOrder,
+  head: usize,
+  tail: usize,
+  len: usize,
+}
+
+impl<'a, T, S, F> KeyedTinySetQueue<'a, T, S, F>
+where
+  S: SetBacking + ?Sized,
+  F: Fn(&T) -> usize,
+{
+  /// Constructs a queue over caller-provided slots and membership, with `key`
+  /// mapping each value to its membership index.
+  ///
+  /// Every slot should start as `None`; pending slots hold `Some`. The backing
+  /// is cleared when the `clear_on_new` feature is enabled, as in
+  /// [`TinySetQueue::new`].
+  pub fn new(
+    buf: &'a mut [Option<T>],
+    in_queue: &'a mut S,
+    key: F,
+    mode: MembershipMode,
+    order: ProcessingOrder,
+  ) -> Self {
+    #[cfg(feature = "clear_on_new")]
+    in_queue.clear_all();
+    KeyedTinySetQueue {
+      buf,
+      in_queue,
+      key,
+      mode,
+      order,
+      head: 0,
+      tail: 0,
+      len: 0,
+    }
+  }
+
+  /// Returns the maximum number of pending items the queue can hold.
+  #[inline]
+  pub fn capacity(&self) -> usize {
+    self.buf.len()
+  }
+
+  /// Returns the number of indices the membership backing can represent;
+  /// every key pushed must be below it.
+  #[inline]
+  pub fn membership_capacity(&self) -> usize {
+    self.in_queue.capacity()
+  }
+
+  /// Returns the number of items currently enqueued.
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Returns `true` when the queue is empty.
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Returns `true` when the queue is at full capacity.
+  #[inline]
+  pub fn is_full(&self) -> bool {
+    self.len == self.buf.len()
+  }
+
+  /// Returns `true` when `value`'s key is marked in the membership backing.
+  pub fn contains(&self, value: &T) -> bool {
+    let index = (self.key)(value);
+    index < self.in_queue.capacity() && self.in_queue.contains(index)
+  }
+
+  /// Pushes a value into the queue unless its key is already present.
+  ///
+  /// # Errors
+  ///
+  /// Returns a [`PushError`] carrying `value` under the same conditions as
+  /// [`TinySetQueue::push`].
+  pub fn push(&mut self, value: T) -> Result<PushResult, PushError<T>> {
+    let index = (self.key)(&value);
+
+    if index >= self.in_queue.capacity() {
+      return Err(PushError::OutOfRange(value));
+    }
+
+    if self.in_queue.contains(index) {
+      return Ok(PushResult::AlreadyPresent);
+    }
+
+    if self.is_full() {
+      return Err(PushError::Full(value));
+    }
+
+    self.buf[self.tail] = Some(value);
+    self.in_queue.insert(index);
+
+    self.tail = (self.tail + 1) % self.buf.len();
+    self.len += 1;
+
+    Ok(PushResult::Inserted)
+  }
+
+  /// Moves the next value out according to the configured processing order,
+  /// if any.
+  ///
+  /// Membership is cleared in [`MembershipMode::InQueue`] and retained in
+  /// [`MembershipMode::Visited`].
+  pub fn pop(&mut self) -> Option<T> {
+    if self.is_empty() {
+      return None;
+    }
+
+    let cap = self.buf.len();
+    let slot = match self.order {
+      ProcessingOrder::Fifo => {
+        let slot = self.head;
+        self.head = (self.head + 1) % cap;
+        slot
+      }
+      ProcessingOrder::Lifo => {
+        self.tail = (self.tail + cap - 1) % cap;
+        self.tail
+      }
+      ProcessingOrder::InsertionMin => {
+        let offset = (0..self.len)
+          .min_by_key(|offset| {
+            let pending = self.buf[(self.head + offset) % cap].as_ref();
+            pending.map_or(usize::MAX, &self.key)
+          })
+          .unwrap_or(0);
+        // Bubble the chosen value to the newest slot, keeping the others in
+        // insertion order, then take it from there.
+        for offset in offset..self.len - 1 {
+          self
+            .buf
+            .swap((self.head + offset) % cap, (self.head + offset + 1) % cap);
+        }
+        self.tail = (self.tail + cap - 1) % cap;
+        self.tail
+      }
+    };
+
+    let value = self.buf[slot].take()?;
+    if matches!(self.mode, MembershipMode::InQueue) {
+      self.in_queue.remove((self.key)(&value));
+    }
+    self.len -= 1;
+
+    Some(value)
+  }
+
+  /// Returns the value the next `pop` would yield, without removing it.
+  pub fn peek(&self) -> Option<&T> {
+    if self.is_empty() {
+      return None;
+    }
+    let cap = self.buf.len();
+    let pending = |offset: usize| self.buf[(self.head + offset) % cap].as_ref();
+    match self.order {
+      ProcessingOrder::Fifo => pending(0),
+      ProcessingOrder::Lifo => pending(self.len - 1),
+      ProcessingOrder::InsertionMin => (0..self.len)
+        .filter_map(pending)
+        .min_by_key(|value| (self.key)(value)),
+    }
+  }
+
+  /// Drops every pending value and clears the membership backing.
+  pub fn clear(&mut self) {
+    for slot in self.buf.iter_mut() {
+      *slot = None;
+    }
+    self.in_queue.clear_all();
+    self.head = 0;
+    self.tail = 0;
+    self.len = 0;
+  }
+}
+
+/// A popped value that is returned to its queue unless committed.
+///
+/// Created by [`TinySetQueue::pop_guarded`]. Dereferences to the popped value.
+pub struct PopGuard<'q, 'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  queue: &'q mut TinySetQueue<'a, T, S>,
+  value: T,
+  /// Insertion-order offset the value was popped from.
+  offset: usize,
+  committed: bool,
+}
+
+impl<'q, 'a, T, S> PopGuard<'q, 'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  /// Confirms the pop and returns the value; it will not be restored.
+  pub fn commit(mut self) -> T {
+    self.committed = true;
+    self.value
+  }
+}
+
+impl<'q, 'a, T, S> core::ops::Deref for PopGuard<'q, 'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.value
+  }
+}
+
+impl<'q, 'a, T, S> Drop for PopGuard<'q, 'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  fn drop(&mut self) {
+    if !self.committed {
+      self.queue.unpop(self.value, self.offset);
+    }
+  }
+}
+
+/// Iterator over the pending values of a queue, in processing order.
+///
+/// Created by [`TinySetQueue::iter`].
+pub struct Iter<'a, T> {
+  buf: &'a [T],
+  head: usize,
+  front: usize,
+  back: usize,
+  order: ProcessingOrder,
+}
+
+impl<'a, T> Iter<'a, T> {
+  fn new(
+    buf: &'a [T],
+    head: usize,
+    len: usize,
+    order: ProcessingOrder,
+  ) -> Self {
+    Iter {
+      buf,
+      head,
+      front: 0,
+      back: len,
+      order,
+    }
+  }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+  type Item = &'a T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.front == self.back {
+      return None;
+    }
+
+    // `front..back` are offsets from `head` in insertion order; LIFO walks
+    // them from the newest end.
+    let offset = match self.order {
+      ProcessingOrder::Fifo | ProcessingOrder::InsertionMin => {
+        let offset = self.front;
+        self.front += 1;
+        offset
+      }
+      ProcessingOrder::Lifo => {
+        self.back -= 1;
+        self.back
+      }
+    };
+
+    Some(&self.buf[(self.head + offset) % self.buf.len()])
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let remaining = self.back - self.front;
+    (remaining, Some(remaining))
+  }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.front == self.back {
+      return None;
+    }
+
+    let offset = match self.order {
+      ProcessingOrder::Fifo | ProcessingOrder::InsertionMin => {
+        self.back -= 1;
+        self.back
+      }
+      ProcessingOrder::Lifo => {
+        let offset = self.front;
+        self.front += 1;
+        offset
+      }
+    };
+
+    Some(&self.buf[(self.head + offset) % self.buf.len()])
+  }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T> core::iter::FusedIterator for Iter<'a, T> {}
+
+/// Iterator that pops the pending values of a queue, in processing order.
+///
+/// Created by [`TinySetQueue::drain`]. Any values left when it is dropped are
+/// popped as well.
+pub struct Drain<'q, 'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  queue: &'q mut TinySetQueue<'a, T, S>,
+}
+
+impl<'q, 'a, T, S> Iterator for Drain<'q, 'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  type Item = T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.queue.pop()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.queue.len, Some(self.queue.len))
+  }
+}
+
+impl<'q, 'a, T, S> ExactSizeIterator for Drain<'q, 'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+}
+
+impl<'q, 'a, T, S> core::iter::FusedIterator for Drain<'q, 'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+}
+
+impl<'q, 'a, T, S> Drop for Drain<'q, 'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  fn drop(&mut self) {
+    while self.queue.pop().is_some() {}
+  }
+}
+
+/// A power-of-two capacity variant that uses bit masking for wrap-around.
+///
+/// As with [`TinySetQueue`], membership is direct-mapped: the membership backing must be
+/// large enough to cover the entire domain addressable by `T::into()`.
+#[cfg(feature = "pow2")]
+pub struct TinySetQueuePow2<'a, T, S>
+where
+  S: SetBacking + ?Sized,
+{
+  buf: &'a mut [T],
+  in_queue: &'a mut S,
+  mode: MembershipMode,
+  order: ProcessingOrder,
+  mask: usize,
+  head: usize,
+  tail: usize,
+  len: usize,
+}
+
+#[cfg(feature = "pow2")]
+impl<'a, T, S> TinySetQueuePow2<'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  /// Constructs a queue backed by power-of-two-sized storage.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `buf.len()` is not a power of two.
+  pub fn new(
+    buf: &'a mut [T],
+    in_queue: &'a mut S,
+    mode: MembershipMode,
+    order: ProcessingOrder,
+  ) -> Self {
+    assert!(
+      buf.len().is_power_of_two(),
+      "buffer length must be a power of two"
+    );
+    #[cfg(feature = "clear_on_new")]
+    in_queue.clear_all();
+    let mask = buf.len() - 1;
+    TinySetQueuePow2 {
+      buf,
+      in_queue,
+      mode,
+      order,
+      mask,
+      head: 0,
+      tail: 0,
+      len: 0,
+    }
+  }
+
+  /// Clears the queue without freeing any backing storage.
+  pub fn clear(&mut self) {
+    self.in_queue.clear_all();
+    self.head = 0;
+    self.tail = 0;
+    self.len = 0;
+  }
+
+  #[inline]
+  pub fn capacity(&self) -> usize {
+    self.buf.len()
+  }
+
+  /// Returns the number of indices the membership backing can represent.
+  ///
+  /// Unlike [`capacity`](Self::capacity), the buffer length, this bounds the
+  /// values that can be pushed: `value.into()` must be below it.
+  #[inline]
+  pub fn membership_capacity(&self) -> usize {
+    self.in_queue.capacity()
+  }
+
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  #[inline]
+  pub fn is_full(&self) -> bool {
+    self.len == self.buf.len()
+  }
+
+  #[inline]
+  pub fn remaining(&self) -> usize {
+    debug_assert!(self.len <= self.buf.len());
+    self.buf.len() - self.len
+  }
+
+  pub fn push(&mut self, value: T) -> Result<PushResult, PushError<T>> {
+    let idx: usize = value.into();
+
+    if idx >= self.in_queue.capacity() {
+      return Err(PushError::OutOfRange(value));
+    }
+
+    if self.in_queue.contains(idx) {
+      return Ok(PushResult::AlreadyPresent);
+    }
+
+    if self.is_full() {
+      return Err(PushError::Full(value));
+    }
+
+    self.buf[self.tail] = value;
+    self.in_queue.insert(idx);
+
+    self.tail = (self.tail + 1) & self.mask;
+    self.len += 1;
+
+    Ok(PushResult::Inserted)
+  }
+
+  pub fn pop(&mut self) -> Option<T> {
+    if self.is_empty() {
+      return None;
+    }
+
+    let value = match self.order {
+      ProcessingOrder::Fifo => {
+        let idx = self.head;
+        self.head = (self.head + 1) & self.mask;
+        self.buf[idx]
+      }
+      ProcessingOrder::Lifo => {
+        let idx = (self.tail.wrapping_sub(1)) & self.mask;
+        self.tail = idx;
+        self.buf[idx]
+      }
+      ProcessingOrder::InsertionMin => {
+        let offset = min_key_offset(self.buf, self.head, self.len);
+        let value = remove_at_offset(self.buf, self.head, self.len, offset);
+        self.tail = (self.tail.wrapping_sub(1)) & self.mask;
+        value
+      }
+    };
+
+    Some(self.finish_pop(value))
+  }
+
+  /// Same as [`push`](Self::push): inserts at the back of the ring.
+  #[inline]
+  pub fn push_back(&mut self, value: T) -> Result<PushResult, PushError<T>> {
+    self.push(value)
+  }
+
+  /// Pushes a value at the front of the ring unless it is already present,
+  /// sharing capacity and membership dedup with [`push`](Self::push).
+  pub fn push_front(&mut self, value: T) -> Result<PushResult, PushError<T>> {
+    let idx: usize = value.into();
+
+    if idx >= self.in_queue.capacity() {
+      return Err(PushError::OutOfRange(value));
+    }
+
+    if self.in_queue.contains(idx) {
+      return Ok(PushResult::AlreadyPresent);
+    }
+
+    if self.is_full() {
+      return Err(PushError::Full(value));
+    }
+
+    self.head = (self.head.wrapping_sub(1)) & self.mask;
+    self.buf[self.head] = value;
+    self.in_queue.insert(idx);
+    self.len += 1;
+
+    Ok(PushResult::Inserted)
+  }
+
+  /// Pops the oldest pending value, whatever the processing order.
+  pub fn pop_front(&mut self) -> Option<T> {
+    if self.is_empty() {
+      return None;
+    }
+    let value = self.buf[self.head];
+    self.head = (self.head + 1) & self.mask;
+    Some(self.finish_pop(value))
+  }
+
+  /// Pops the newest pending value, whatever the processing order.
+  pub fn pop_back(&mut self) -> Option<T> {
+    if self.is_empty() {
+      return None;
+    }
+    self.tail = (self.tail.wrapping_sub(1)) & self.mask;
+    Some(self.finish_pop(self.buf[self.tail]))
+  }
+
+  /// Updates membership and `len` for a value just taken out of the ring.
+  fn finish_pop(&mut self, value: T) -> T {
+    if matches!(self.mode, MembershipMode::InQueue) {
+      self.in_queue.remove(value.into());
+    }
+    self.len -= 1;
+    value
+  }
+
+  /// Returns an iterator over the pending values in processing order.
+  pub fn iter(&self) -> Iter<'_, T> {
+    Iter::new(self.buf, self.head, self.len, self.order)
+  }
+
+  /// Returns an iterator over `(value.into(), &value)` pairs in processing order.
+  pub fn iter_indexed(&self) -> impl Iterator<Item = (usize, &T)> + '_ {
+    self.iter().map(|value| ((*value).into(), value))
+  }
+
+  /// Migrates the queue into a larger power-of-two buffer and membership backing.
+  ///
+  /// Pending values are copied in order to the start of `new_buf`, every index
+  /// present in the current membership backing is re-marked in `new_membership`
+  /// (after clearing it), and the mask is recomputed for the new length.
+  ///
+  /// # Errors
+  ///
+  /// Returns `Err(self)` untouched if `new_buf.len()` is not a power of two, is
+  /// smaller than `len()`, or if `new_membership` cannot represent an index
+  /// currently present in the membership backing.
+  pub fn grow_pow2(
+    self,
+    new_buf: &'a mut [T],
+    new_membership: &'a mut S,
+  ) -> Result<TinySetQueuePow2<'a, T, S>, Self> {
+    if !new_buf.len().is_power_of_two() || new_buf.len() < self.len {
+      return Err(self);
+    }
+
+    let mut fits = true;
+    for_each_set_bit(&*self.in_queue, |idx| {
+      fits &= idx < new_membership.capacity();
+    });
+    if !fits {
+      return Err(self);
+    }
+
+    new_membership.clear_all();
+    for_each_set_bit(&*self.in_queue, |idx| new_membership.insert(idx));
+    for (offset, slot) in new_buf[..self.len].iter_mut().enumerate() {
+      *slot = self.buf[(self.head + offset) & self.mask];
+    }
+
+    let mask = new_buf.len() - 1;
+    Ok(TinySetQueuePow2 {
+      buf: new_buf,
+      in_queue: new_membership,
+      mode: self.mode,
+      order: self.order,
+      mask,
+      head: 0,
+      tail: self.len & mask,
+      len: self.len,
+    })
+  }
+
+  /// Returns the value the next `pop` would yield, without removing it.
+  ///
+  /// Nothing is modified: the ring indices and membership are untouched.
+  /// Returns `None` when the queue is empty. Under
+  /// [`ProcessingOrder::InsertionMin`] this scans for the smallest key.
+  pub fn peek(&self) -> Option<&T> {
+    if self.is_empty() {
+      return None;
+    }
+    let offset = match self.order {
+      ProcessingOrder::Fifo => 0,
+      ProcessingOrder::Lifo => self.len - 1,
+      ProcessingOrder::InsertionMin => {
+        min_key_offset(self.buf, self.head, self.len)
+      }
+    };
+    Some(&self.buf[(self.head + offset) & self.mask])
+  }
+
+  /// Returns the oldest pending value, whatever the processing order.
+  #[inline]
+  pub fn front(&self) -> Option<&T> {
+    if self.is_empty() {
+      return None;
+    }
+    Some(&self.buf[self.head])
+  }
+
+  /// Returns the newest pending value, whatever the processing order.
+  #[inline]
+  pub fn back(&self) -> Option<&T> {
+    if self.is_empty() {
+      return None;
+    }
+    Some(&self.buf[self.tail.wrapping_sub(1) & self.mask])
+  }
+
+  /// Returns the pending value at `logical` positions from the pop side, or
+  /// `None` if `logical >= len`.
+  ///
+  /// Position 0 is the value the next `pop` takes under FIFO (the oldest) and
+  /// LIFO (the newest), so `get(i)` agrees with `iter().nth(i)`. Under
+  /// [`ProcessingOrder::InsertionMin`] positions follow insertion order, like
+  /// [`iter`](Self::iter).
+  #[inline]
+  pub fn get(&self, logical: usize) -> Option<&T> {
+    if logical >= self.len {
+      return None;
+    }
+    let offset = match self.order {
+      ProcessingOrder::Fifo | ProcessingOrder::InsertionMin => logical,
+      ProcessingOrder::Lifo => self.len - 1 - logical,
+    };
+    Some(&self.buf[(self.head + offset) & self.mask])
+  }
+
+  /// Returns `true` when `value` is marked in the membership backing.
+  ///
+  /// In [`MembershipMode::InQueue`] this means `value` is pending. In
+  /// [`MembershipMode::Visited`] the flag outlives the buffer, so this also
+  /// returns `true` for values that were pushed and already popped, which
+  /// makes the queue usable as a "seen" set. Values whose index is beyond the
+  /// membership backing return `false`.
+  pub fn contains(&self, value: T) -> bool {
+    let idx: usize = value.into();
+    idx < self.in_queue.capacity() && self.in_queue.contains(idx)
+  }
+
+  /// Returns `true` when `value` is marked in the membership backing; the same
+  /// check as [`contains`](Self::contains) under a name that reads well in
+  /// [`MembershipMode::Visited`], where the mark means "pushed at some point",
+  /// not "still pending". See [`is_pending`](Self::is_pending).
+  #[inline]
+  pub fn is_visited(&self, value: T) -> bool {
+    self.contains(value)
+  }
+
+  /// Returns `true` only when `value` currently occupies a buffer slot.
+  ///
+  /// In [`MembershipMode::InQueue`] the membership flag already means this, so
+  /// the check is O(1). In [`MembershipMode::Visited`] a marked value may have
+  /// been popped, so this scans the pending values: O(len) for marked values,
+  /// O(1) for unmarked ones.
+  pub fn is_pending(&self, value: T) -> bool {
+    if !self.contains(value) {
+      return false;
+    }
+    match self.mode {
+      MembershipMode::InQueue => true,
+      MembershipMode::Visited => {
+        let idx: usize = value.into();
+        self.iter().any(|pending| (*pending).into() == idx)
+      }
+    }
+  }
+
+  /// Keeps only the pending values for which `keep` returns `true`, in one
+  /// pass over the ring.
+  ///
+  /// Behaves like [`TinySetQueue::retain`]: removed values have their
+  /// membership cleared in both modes and kept values stay in order.
+  pub fn retain<F>(&mut self, mut keep: F)
+  where
+    F: FnMut(&T) -> bool,
+  {
+    let mut kept = 0;
+    for offset in 0..self.len {
+      let value = self.buf[(self.head + offset) & self.mask];
+      if keep(&value) {
+        self.buf[(self.head + kept) & self.mask] = value;
+        kept += 1;
+      } else {
+        self.in_queue.remove(value.into());
+      }
+    }
+    self.len = kept;
+    self.tail = (self.head + kept) & self.mask;
+  }
+
+  /// Switches the membership mode, keeping any pending values and flags.
+  ///
+  /// Behaves like [`TinySetQueue::set_mode`]: only later pops are affected,
+  /// so pending values become re-enqueueable once popped after switching from
+  /// `Visited` to `InQueue`.
+  #[inline]
+  pub fn set_mode(&mut self, mode: MembershipMode) {
+    self.mode = mode;
+  }
+}
+
+#[cfg(feature = "pow2")]
+impl<'a, T, S> private::SealedView for TinySetQueuePow2<'a, T, S> where
+  S: SetBacking + ?Sized
+{
+}
+
+#[cfg(feature = "pow2")]
+impl<'a, T, S> QueueView<T> for TinySetQueuePow2<'a, T, S>
+where
+  T: Copy + Into<usize>,
+  S: SetBacking + ?Sized,
+{
+  fn len(&self) -> usize {
+    self.len
+  }
+
+  fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  fn is_full(&self) -> bool {
+    self.len == self.buf.len()
+  }
+
+  fn capacity(&self) -> usize {
+    self.buf.len()
+  }
+
+  fn peek(&self) -> Option<&T> {
+    TinySetQueuePow2::peek(self)
+  }
+
+  fn contains(&self, value: T) -> bool {
+    TinySetQueuePow2::contains(self, value)
+  }
+
+  fn iter(&self) -> Iter<'_, T> {
+    Iter::new(self.buf, self.head, self.len, self.order)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    CaptureError, MembershipMode, ProcessingOrder, PushError, PushOutcome,
+    PushResult, QueueView, TinySetQueue, WideKey,
+  };
+
+  #[test]
+  fn basic_push_pop_in_queue() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert!(queue.is_empty());
+    assert_eq!(queue.capacity(), 4);
+
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(1), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.len(), 1);
+
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.len(), 0);
+
+    // Membership cleared in InQueue mode -> can be inserted again.
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+  }
+
+  #[test]
+  fn visited_mode_prevents_requeue() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(2));
+    assert_eq!(queue.push(2), Ok(PushResult::AlreadyPresent));
+  }
+
+  #[test]
+  fn lifo_order_pops_most_recent() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Lifo,
+    );
+
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(3), Ok(PushResult::Inserted));
+    assert_eq!(queue.len(), 3);
+
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.pop(), Some(2));
+    assert_eq!(queue.pop(), Some(1));
+    assert!(queue.is_empty());
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+  }
+
+  #[test]
+  fn clear_resets_membership_and_indices() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert!(queue.is_full());
+
+    queue.clear();
+    assert!(queue.is_empty());
+    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+  }
+
+  #[cfg(feature = "clear_on_new")]
+  #[test]
+  fn new_clears_membership_bitmap() {
+    let mut buf = [0u8; 2];
+    let mut membership = [true; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert!(queue.is_empty());
+    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
+  }
+
+  #[cfg(not(feature = "clear_on_new"))]
+  #[test]
+  fn new_preserves_membership_bitmap() {
+    let mut buf = [0u8; 2];
+    let mut membership = [true; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert!(queue.is_empty());
+    assert_eq!(queue.push(0), Ok(PushResult::AlreadyPresent));
+  }
+
+  #[test]
+  fn push_rejects_out_of_range_index() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 2];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.push(3), Err(PushError::OutOfRange(3)));
+    assert!(queue.is_empty());
+  }
+
+  #[test]
+  fn push_rejects_when_full() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert!(queue.is_full());
+    assert_eq!(queue.push(2), Err(PushError::Full(2)));
+    assert_eq!(queue.len(), 2);
+  }
+
+  #[test]
+  fn ring_buffer_wraparound_preserves_membership() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 6];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert!(queue.is_full());
+
+    assert_eq!(queue.pop(), Some(0));
+    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), Some(2));
+    assert_eq!(queue.pop(), Some(0));
+    assert!(queue.is_empty());
+  }
+
+  #[test]
+  fn zero_capacity_queue_behaves_consistently() {
+    let mut buf: [u8; 0] = [];
+    let mut membership = [false; 1];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.capacity(), 0);
+    assert!(queue.is_empty());
+    assert!(queue.is_full());
+    assert_eq!(queue.push(0), Err(PushError::Full(0)));
+    assert_eq!(queue.pop(), None);
+  }
+
+  #[test]
+  fn bitset_backing_handles_high_indices() {
+    let mut buf = [0u16; 4];
+    let mut membership = [0u64; 2]; // capacity 128
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(63), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(63), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.push(64), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(0));
+    assert_eq!(queue.push(0), Ok(PushResult::Inserted)); // membership cleared after pop
+  }
+
+  #[test]
+  fn bitset_backing_enforces_capacity() {
+    let mut buf = [0u8; 2];
+    let mut membership = [0u64; 1]; // capacity 64
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.push(63), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(64), Err(PushError::OutOfRange(64))); // out of range
+  }
+
+  #[test]
+  fn bitset_visited_mode_persists_membership() {
+    let mut buf = [0u8; 2];
+    let mut membership = [0u64; 1];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.push(10), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(10));
+    assert_eq!(queue.push(10), Ok(PushResult::AlreadyPresent));
+  }
+
+  #[test]
+  fn iter_indexed_matches_keys_across_wraparound() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(6), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(5));
+    assert_eq!(queue.push(7), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted)); // wraps to slot 0
+
+    let mut pairs = queue.iter_indexed();
+    assert_eq!(pairs.next(), Some((6, &6)));
+    assert_eq!(pairs.next(), Some((7, &7)));
+    assert_eq!(pairs.next(), Some((1, &1)));
+    assert_eq!(pairs.next(), None);
+    assert_eq!(queue.len(), 3);
+  }
+
+  #[test]
+  fn iter_follows_lifo_processing_order() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Lifo,
+    );
+
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(3), Ok(PushResult::Inserted));
+
+    let mut iter = queue.iter();
+    assert_eq!(iter.size_hint(), (3, Some(3)));
+    assert_eq!(iter.next(), Some(&3));
+    assert_eq!(iter.next(), Some(&2));
+    assert_eq!(iter.next(), Some(&1));
+    assert_eq!(iter.next(), None);
+  }
+
+  #[test]
+  fn pop_guard_restores_uncommitted_values() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.push(4), Ok(PushResult::Inserted)); // wraps to slot 0
+
+    {
+      let guard = queue.pop_guarded().unwrap();
+      assert_eq!(*guard, 2);
+    }
+    assert_eq!(queue.len(), 3);
+    assert_eq!(queue.push(2), Ok(PushResult::AlreadyPresent));
+
+    let guard = queue.pop_guarded().unwrap();
+    assert_eq!(guard.commit(), 2);
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.pop(), Some(4));
+    assert_eq!(queue.pop(), Some(2));
+  }
+
+  #[test]
+  fn pop_guard_restores_insertion_min_position() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::InsertionMin,
+    );
+    for value in [5, 9, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    queue.set_order(ProcessingOrder::Fifo);
+    assert_eq!(queue.pop(), Some(5));
+    queue.set_order(ProcessingOrder::InsertionMin);
+    for value in [7, 2] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    let pending = |queue: &TinySetQueue<'_, u8, [bool; 16]>| {
+      let mut seen = [0u8; 4];
+      for (slot, value) in seen.iter_mut().zip(queue.iter()) {
+        *slot = *value;
+      }
+      seen
+    };
+
+    // 2 is the newest value and sits in the wrapped slot.
+    drop(queue.pop_guarded());
+    assert_eq!(pending(&queue), [9, 3, 7, 2]);
+
+    // 3 is in the middle; restoring it shifts 7 and 8 across the wrap.
+    assert_eq!(queue.pop(), Some(2));
+    assert_eq!(queue.push(8), Ok(PushResult::Inserted));
+    drop(queue.pop_guarded());
+    assert_eq!(pending(&queue), [9, 3, 7, 8]);
+    assert!(queue.contains(3));
+    for expected in [3, 7, 8, 9] {
+      assert_eq!(queue.pop(), Some(expected));
+    }
+  }
+
+  #[test]
+  fn pop_guard_restores_lifo_top() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Lifo,
+    );
+
+    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    drop(queue.pop_guarded());
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), Some(0));
+    assert_eq!(queue.push(1), Ok(PushResult::AlreadyPresent));
+  }
+
+  fn summarize<V: QueueView<u8>>(view: &V) -> (usize, Option<u8>, bool) {
+    (view.len(), view.peek().copied(), view.contains(3))
+  }
+
+  #[test]
+  fn queue_view_exposes_read_only_state() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Lifo,
+    );
+
+    assert_eq!(summarize(&queue), (0, None, false));
+    assert_eq!(queue.push(3), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+    assert_eq!(summarize(&queue), (2, Some(5), true));
+    assert!(!QueueView::contains(&queue, 200)); // out of range reads as absent
+    assert_eq!(QueueView::iter(&queue).count(), 2);
+    assert_eq!(queue.len(), 2);
+  }
+
+  #[test]
+  fn normalize_moves_unwrapped_contents_to_front() {
+    let mut buf = [0u8; 5];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    for value in [1, 2, 3, 4] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), Some(2));
+
+    queue.normalize();
+    assert_eq!(queue.make_contiguous(), &[3, 4]);
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.pop(), Some(4));
+    assert_eq!(queue.pop(), Some(5));
+  }
+
+  #[test]
+  fn normalize_rotates_wrapped_contents() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.push(4), Ok(PushResult::Inserted)); // wraps to slot 0
+
+    queue.normalize();
+    assert_eq!(queue.make_contiguous(), &[2, 3, 4]);
+    assert_eq!(queue.pop(), Some(2));
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.pop(), Some(4));
+    assert!(queue.is_empty());
+  }
+
+  #[test]
+  fn pop_if_leaves_queue_unchanged_on_rejection() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.pop_if(|_| true), None);
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(7), Ok(PushResult::Inserted));
+
+    assert_eq!(queue.pop_if(|&value| value > 5), None);
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.push(2), Ok(PushResult::AlreadyPresent));
+
+    assert_eq!(queue.pop_if(|&value| value < 5), Some(2));
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(7));
+  }
+
+  #[test]
+  fn take_front_reports_short_batches() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    for value in [4, 5, 6] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    assert_eq!(queue.take_front::<2>(), ([4, 5], 2));
+    assert_eq!(queue.take_front::<4>(), ([6, 0, 0, 0], 1));
+    assert!(queue.is_empty());
+    assert_eq!(queue.push(4), Ok(PushResult::Inserted));
+  }
+
+  #[test]
+  fn members_subset_and_superset_handle_differing_capacities() {
+    let mut buf = [0u8; 4];
+    let mut membership = [0u64; 2]; // capacity 128
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+
+    for value in [1, 3, 70] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    let mut first_pass = [false; 8];
+    first_pass[1] = true;
+    first_pass[3] = true;
+    assert!(queue.members_superset_of(&first_pass));
+    assert!(!queue.members_subset_of(&first_pass)); // 70 is outside first_pass
+
+    first_pass[5] = true;
+    assert!(!queue.members_superset_of(&first_pass));
+
+    let wide = [u64::MAX; 2];
+    assert!(queue.members_subset_of(&wide));
+    assert!(queue.members_superset_of(&[false; 0]));
+  }
+
+  #[test]
+  fn members_intersection_count_covers_overlap_cases() {
+    let mut buf = [0u8; 4];
+    let mut membership = [0u64; 2];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+
+    for value in [2, 9, 64, 100] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    let identical = [(1u64 << 2) | (1 << 9), (1 << 0) | (1 << 36)];
+    assert_eq!(queue.members_intersection_count(&identical), 4);
+
+    let mut partial = [false; 80];
+    partial[9] = true;
+    partial[64] = true;
+    partial[70] = true;
+    assert_eq!(queue.members_intersection_count(&partial), 2);
+
+    let disjoint = [1u64 << 3];
+    assert_eq!(queue.members_intersection_count(&disjoint), 0);
+  }
+
+  #[cfg(feature = "alloc")]
+  #[test]
+  fn drain_indices_sorted_yields_ascending_indices() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    for value in [9, 2, 14, 5] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    let indices: alloc::vec::Vec<usize> =
+      queue.drain_indices_sorted().collect();
+    assert_eq!(indices, [2, 5, 9, 14]);
+    assert!(queue.is_empty());
+    assert_eq!(queue.push(9), Ok(PushResult::Inserted));
+  }
+
+  #[test]
+  fn fold_pending_matches_drained_fold() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.push(4), Ok(PushResult::Inserted)); // wraps to slot 0
+
+    // Order-sensitive fold: digits in processing order.
+    let folded = queue.fold_pending(0u32, |acc, &v| acc * 10 + u32::from(v));
+    assert_eq!(folded, 234);
+    assert_eq!(queue.len(), 3);
+
+    let mut drained = 0u32;
+    while let Some(v) = queue.pop() {
+      drained = drained * 10 + u32::from(v);
+    }
+    assert_eq!(drained, folded);
+  }
+
+  #[cfg(all(feature = "alias_check", debug_assertions))]
+  #[test]
+  fn alias_check_counts_double_registration() {
+    let backing = [0u64; 1];
+    let addr = super::backing_addr(&backing);
+    let before = super::aliased_backings();
+    let token = super::alias::BackingToken::register(addr);
+    drop(super::alias::BackingToken::register(addr));
+    assert!(super::aliased_backings() > before);
+    drop(token);
+  }
+
+  #[cfg(all(feature = "alias_check", debug_assertions))]
+  #[test]
+  fn alias_check_tolerates_forgotten_queue() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 4];
+    let queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    core::mem::forget(queue);
+
+    // The forgotten registration lingers; it is counted, not fatal.
+    let before = super::aliased_backings();
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    assert!(super::aliased_backings() > before);
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(1));
+  }
+
+  #[cfg(all(feature = "alias_check", debug_assertions))]
+  #[test]
+  fn alias_check_releases_backing_on_drop() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 4];
+    for _ in 0..2 {
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        MembershipMode::InQueue,
+        ProcessingOrder::Fifo,
+      );
+      assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    }
+  }
+
+  #[cfg(feature = "alloc")]
+  #[test]
+  fn pop_n_returns_fewer_when_queue_empties() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Lifo,
+    );
+
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    assert_eq!(queue.pop_n(2), [3, 2]);
+    assert_eq!(queue.pop_n(5), [1]);
+    assert!(queue.pop_n(1).is_empty());
+    assert_eq!(queue.push(3), Ok(PushResult::AlreadyPresent));
+  }
+
+  #[test]
+  fn set_indices_validates_ring_state() {
+    let mut buf = [10u8, 11, 12, 13];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.set_indices(4, 0, 0), Err("head is out of bounds"));
+    assert_eq!(queue.set_indices(0, 4, 0), Err("tail is out of bounds"));
+    assert_eq!(queue.set_indices(0, 0, 5), Err("len exceeds capacity"));
+    assert_eq!(
+      queue.set_indices(3, 1, 3),
+      Err("len does not match the distance from head to tail")
+    );
+    assert!(queue.is_empty());
+
+    // Wrapped window: slots 3, 0, 1.
+    assert_eq!(queue.set_indices(3, 2, 3), Ok(()));
+    assert_eq!(queue.pop(), Some(13));
+    assert_eq!(queue.pop(), Some(10));
+    assert_eq!(queue.pop(), Some(11));
+    assert_eq!(queue.pop(), None);
+
+    // Full ring: head == tail.
+    assert_eq!(queue.set_indices(2, 2, 4), Ok(()));
+    assert!(queue.is_full());
+  }
+
+  #[test]
+  fn set_indices_on_zero_capacity_queue() {
+    let mut buf: [u8; 0] = [];
+    let mut membership = [false; 1];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.set_indices(0, 0, 0), Ok(()));
+    assert!(queue.set_indices(0, 0, 1).is_err());
+  }
+
+  #[test]
+  fn members_difference_into_materializes_difference() {
+    let mut buf = [0u8; 4];
+    let mut membership = [0u64; 2];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+
+    for value in [1, 5, 64, 90] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    let mut other = [false; 70];
+    other[5] = true;
+    other[64] = true;
+    let mut out = [true; 128];
+    queue.members_difference_into(&other, &mut out);
+    assert!((0..128).all(|i| out[i] == (i == 1 || i == 90)));
+
+    let mut disjoint_out = [0u64; 2];
+    queue.members_difference_into(&[0u64; 1], &mut disjoint_out);
+    assert_eq!(disjoint_out, [(1 << 1) | (1 << 5), (1 << 0) | (1 << 26)]);
+  }
+
+  #[cfg(feature = "arbitrary")]
+  #[test]
+  fn arbitrary_ops_drive_queue_consistently() {
+    use super::fuzz::Op;
+    use arbitrary::{Arbitrary, Unstructured};
+
+    let bytes: Vec<u8> = (0..=255u8).cycle().take(512).collect();
+    let mut u = Unstructured::new(&bytes);
+    let mut ops = Vec::new();
+    while !u.is_empty() {
+      ops.push(Op::<u8>::arbitrary(&mut u).unwrap());
+    }
+    assert!(ops.iter().any(|op| matches!(op, Op::Push(_))));
+    assert!(ops.contains(&Op::Pop));
+
+    let mut buf = [0u8; 8];
+    let mut membership = [false; 256];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    for op in ops {
+      if let Some(value) = op.apply(&mut queue) {
+        // InQueue mode releases membership on pop.
+        assert!(!QueueView::contains(&queue, value));
+      }
+      assert!(queue.len() <= queue.capacity());
+    }
+  }
+
+  #[test]
+  fn try_push_front_wraps_and_reports_errors() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    // head starts at slot 0, so the first front push wraps to the last slot.
+    assert_eq!(queue.try_push_front(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.try_push_front(0), Ok(PushResult::Inserted));
+    assert_eq!(queue.try_push_front(2), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.try_push_front(3), Err(PushError::Full(3)));
+    assert_eq!(queue.try_push_front(9), Err(PushError::OutOfRange(9)));
+    assert_eq!(queue.push_front(9), Err(PushError::OutOfRange(9)));
+
+    assert_eq!(queue.pop(), Some(0));
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), Some(2));
+    assert!(queue.is_empty());
+  }
+
+  #[cfg(feature = "audit")]
+  #[test]
+  fn audit_records_visited_pops() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 32];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+
+    for round in 0..super::AUDIT_CAPACITY as u8 + 2 {
+      assert_eq!(queue.push(round), Ok(PushResult::Inserted));
+      assert_eq!(queue.pop(), Some(round));
+    }
+    let recent = queue.recently_popped();
+    assert_eq!(recent.len(), super::AUDIT_CAPACITY);
+    assert_eq!(recent.first(), Some(&2));
+    assert_eq!(recent.last(), Some(&(super::AUDIT_CAPACITY + 1)));
+
+    assert_eq!(queue.push(30), Ok(PushResult::Inserted));
+    drop(queue.pop_guarded());
+    assert_eq!(
+      queue.recently_popped().last(),
+      Some(&(super::AUDIT_CAPACITY + 1))
+    );
+
+    queue.clear();
+    assert!(queue.recently_popped().is_empty());
+  }
+
+  #[test]
+  fn push_mapped_uses_supplied_index() {
+    let mut buf = [0u8; 2];
+    let mut membership = [0u64; 1];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.membership_capacity(), 64);
+    assert_eq!(queue.push_mapped(7, 7), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(7), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.push_mapped(9, 9), Ok(PushResult::Inserted));
+    assert_eq!(queue.push_mapped(11, 11), Err(PushError::Full(11))); // full
+    assert_eq!(queue.pop(), Some(7));
+    assert_eq!(queue.push_mapped(7, 7), Ok(PushResult::Inserted));
+  }
+
+  #[test]
+  fn can_push_front_and_back_track_shared_capacity() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert!(queue.can_push_front() && queue.can_push_back());
+    assert_eq!(queue.push_front(1), Ok(PushResult::Inserted));
+    assert!(queue.can_push_front() && queue.can_push_back());
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert!(!queue.can_push_front());
+    assert!(!queue.can_push_back());
+  }
+
+  #[cfg(feature = "alloc")]
+  #[test]
+  fn into_owned_replays_pop_sequence() {
+    let mut buf = [0u8; 3];
+    let mut membership = [0u64; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+
+    for value in [3, 70, 5] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.push(9), Ok(PushResult::Inserted)); // wraps to slot 0
+
+    let mut owned = queue.into_owned();
+    assert_eq!(owned.capacity(), 3);
+    assert_eq!(owned.membership_capacity(), 256);
+    assert_eq!(owned.push(3), Ok(PushResult::AlreadyPresent));
+
+    let expected: alloc::vec::Vec<u8> =
+      core::iter::from_fn(|| queue.pop()).collect();
+    let replayed: alloc::vec::Vec<u8> =
+      core::iter::from_fn(|| owned.pop()).collect();
+    assert_eq!(replayed, expected);
+    assert_eq!(replayed, [70, 5, 9]);
+    assert_eq!(owned.push(70), Ok(PushResult::AlreadyPresent));
+    assert_eq!(owned.push(200), Ok(PushResult::Inserted));
+  }
+
+  #[cfg(feature = "alloc")]
+  #[test]
+  fn into_owned_keeps_membership_domain_of_empty_queue() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 100];
+    let queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    let mut owned = queue.into_owned();
+    assert!(owned.membership_capacity() >= 100);
+    assert_eq!(owned.push(5), Ok(PushResult::Inserted));
+    assert_eq!(owned.push(99), Ok(PushResult::Inserted));
+    assert_eq!(owned.pop(), Some(5));
+  }
+
+  #[test]
+  fn members_for_each_visits_set_indices_in_order() {
+    let mut buf = [0u8; 4];
+    let mut membership = [0u64; 2];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Lifo,
+    );
+
+    for value in [127, 0, 64, 63] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(63));
+
+    let mut seen = [0usize; 4];
+    let mut count = 0;
+    queue.members_for_each(|idx| {
+      seen[count] = idx;
+      count += 1;
+    });
+    assert_eq!(seen, [0, 63, 64, 127]);
+  }
+
+  #[test]
+  fn swap_contents_with_exchanges_frontiers() {
+    let mut current_buf = [0u8; 3];
+    let mut current_members = [0u64; 1];
+    let mut next_buf = [0u8; 4];
+    let mut next_members = [0u64; 1];
+    let mut current = TinySetQueue::new(
+      &mut current_buf,
+      &mut current_members,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    let mut next = TinySetQueue::new(
+      &mut next_buf,
+      &mut next_members,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    for value in [1, 2, 3] {
+      assert_eq!(current.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(current.pop(), Some(1));
+    assert_eq!(current.push(4), Ok(PushResult::Inserted)); // wraps
+    assert_eq!(next.push(9), Ok(PushResult::Inserted));
+
+    assert_eq!(current.swap_contents_with(&mut next), Ok(()));
+    assert_eq!(current.len(), 1);
+    assert_eq!(next.len(), 3);
+    assert_eq!(current.push(9), Ok(PushResult::AlreadyPresent));
+    assert_eq!(current.push(2), Ok(PushResult::Inserted));
+    assert_eq!(next.push(4), Ok(PushResult::AlreadyPresent));
+    assert_eq!(next.pop(), Some(2));
+    assert_eq!(next.pop(), Some(3));
+    assert_eq!(next.pop(), Some(4));
+    assert_eq!(current.pop(), Some(9));
+    assert_eq!(current.pop(), Some(2));
+  }
+
+  #[test]
+  fn swap_contents_with_rejects_mismatched_sizes() {
+    let mut small_buf = [0u8; 1];
+    let mut small_members = [false; 8];
+    let mut big_buf = [0u8; 4];
+    let mut big_members = [false; 8];
+    let mut small = TinySetQueue::new(
+      &mut small_buf,
+      &mut small_members,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    let mut big = TinySetQueue::new(
+      &mut big_buf,
+      &mut big_members,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(big.push(1), Ok(PushResult::Inserted));
+    assert_eq!(big.push(2), Ok(PushResult::Inserted));
+    assert!(small.swap_contents_with(&mut big).is_err());
+    assert_eq!(big.len(), 2);
+    assert!(small.is_empty());
+  }
+
+  #[test]
+  fn peek_sorted_indices_sorts_without_draining() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    for value in [12, 3, 8, 1] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    let mut out = [usize::MAX; 6];
+    assert_eq!(queue.peek_sorted_indices(&mut out), 4);
+    assert_eq!(out, [1, 3, 8, 12, usize::MAX, usize::MAX]);
+    assert_eq!(queue.len(), 4);
+
+    let mut short = [0usize; 2];
+    assert_eq!(queue.peek_sorted_indices(&mut short), 2);
+    assert_eq!(short, [3, 12]);
+    assert_eq!(queue.pop(), Some(12));
+  }
+
+  #[cfg(all(feature = "leak_check", debug_assertions))]
+  #[test]
+  fn leak_check_counts_undrained_in_queue_drops() {
+    let before = super::leaked_queue_drops();
+    {
+      let mut buf = [0u8; 2];
+      let mut membership = [false; 4];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        MembershipMode::InQueue,
+        ProcessingOrder::Fifo,
+      );
+      assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    }
+    // Other tests may leak concurrently; the counter only ever grows.
+    assert!(super::leaked_queue_drops() > before);
+  }
+
+  #[test]
+  fn first_out_of_range_reports_first_offender() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.first_out_of_range(&[1, 7, 9, 12]), Some((2, 9)));
+    assert!(!queue.all_in_range(&[1, 7, 9, 12]));
+    assert_eq!(queue.first_out_of_range(&[0, 3, 7]), None);
+    assert!(queue.all_in_range(&[0, 3, 7]));
+    assert_eq!(queue.first_out_of_range(&[]), None);
+    assert!(queue.is_empty());
+  }
+
+  #[test]
+  fn pop_contiguous_run_stops_at_first_gap() {
+    let mut buf = [0usize; 8];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    for value in [4, 5, 6, 9, 10, 2] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    assert_eq!(queue.pop_contiguous_run(), Some((4, 3)));
+    assert!(!queue.contains(5));
+    assert_eq!(queue.pop_contiguous_run(), Some((9, 2)));
+    // A descending step breaks the run.
+    assert_eq!(queue.pop_contiguous_run(), Some((2, 1)));
+    assert_eq!(queue.pop_contiguous_run(), None);
+  }
+
+  #[test]
+  fn pop_contiguous_run_follows_lifo_order() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Lifo,
+    );
+    for value in [3, 2, 1] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    assert_eq!(queue.pop_contiguous_run(), Some((1, 3)));
+    assert!(queue.contains(2));
+    assert!(queue.is_empty());
+  }
+
+  #[test]
+  fn clear_up_to_bounds_cleared_region() {
+    use super::SetBacking;
+
+    let mut flags = [true; 8];
+    flags.clear_up_to(3);
+    assert_eq!(flags, [false, false, false, true, true, true, true, true]);
+    flags.clear_up_to(100);
+    assert_eq!(flags, [false; 8]);
+
+    let mut words = [u64::MAX; 3];
+    words.clear_up_to(65);
+    assert_eq!(words, [0, 0, u64::MAX]);
+    words.clear_up_to(0);
+    assert_eq!(words, [0, 0, u64::MAX]);
+  }
+
+  #[cfg(feature = "clear_on_new")]
+  #[test]
+  fn clear_tracks_high_water_mark() {
+    let mut buf = [0usize; 4];
+    let mut membership = [0u64; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.high_water, 0);
+
+    assert_eq!(queue.push(3), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(70), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+    assert_eq!(queue.high_water, 71);
+    assert_eq!(queue.pop(), Some(3));
+
+    queue.clear();
+    assert_eq!(queue.high_water, 0);
+    assert!(!queue.contains(3));
+    assert!(!queue.contains(70));
+    assert_eq!(queue.push(70), Ok(PushResult::Inserted));
+  }
+
+  #[test]
+  fn iter_rev_reverses_across_wrap_around() {
+    for order in [ProcessingOrder::Fifo, ProcessingOrder::Lifo] {
+      let mut buf = [0usize; 4];
+      let mut membership = [false; 16];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        MembershipMode::InQueue,
+        order,
+      );
+      for value in [1, 2, 3] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+      queue.pop();
+      queue.pop();
+      for value in [4, 5, 6] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+
+      let mut forward = [0usize; 4];
+      let mut backward = [0usize; 4];
+      for (slot, value) in forward.iter_mut().zip(queue.iter()) {
+        *slot = *value;
+      }
+      for (slot, value) in backward.iter_mut().zip(queue.iter().rev()) {
+        *slot = *value;
+      }
+      backward.reverse();
+      assert_eq!(forward, backward);
+
+      // Mixed ends meet in the middle without overlap.
+      let mut iter = queue.iter();
+      assert_eq!(iter.next(), Some(&forward[0]));
+      assert_eq!(iter.next_back(), Some(&forward[3]));
+      assert_eq!(iter.next(), Some(&forward[1]));
+      assert_eq!(iter.next_back(), Some(&forward[2]));
+      assert_eq!(iter.next(), None);
+      assert_eq!(iter.next_back(), None);
+      queue.clear();
+    }
+  }
+
+  #[test]
+  fn push_or_promote_moves_pending_entry_to_front() {
+    for order in [ProcessingOrder::Fifo, ProcessingOrder::Lifo] {
+      let mut buf = [0usize; 4];
+      let mut membership = [false; 8];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        MembershipMode::Visited,
+        order,
+      );
+      for value in [1, 2, 3] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+      let first = queue.pop().unwrap();
+      assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+      assert_eq!(queue.push(2), Ok(PushResult::AlreadyPresent));
+
+      assert_eq!(queue.push_or_promote(first), Ok(PushResult::AlreadyPresent));
+      assert_eq!(queue.push_or_promote(6), Ok(PushResult::Inserted));
+      assert_eq!(queue.push_or_promote(5), Ok(PushResult::Refreshed));
+      assert_eq!(queue.push_or_promote(5), Ok(PushResult::AlreadyPresent));
+      assert_eq!(queue.pop(), Some(5));
+      assert_eq!(queue.len(), 3);
+    }
+  }
+
+  #[cfg(feature = "scratch_log")]
+  #[test]
+  fn new_with_scratch_clears_recorded_indices() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 16];
+    let mut scratch = [0usize; 2];
+    let mut queue = TinySetQueue::new_with_scratch(
+      &mut buf,
+      &mut membership,
+      &mut scratch,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.push(3), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(9), Ok(PushResult::Inserted));
+    queue.clear();
+    assert!(!queue.contains(3) && !queue.contains(9));
+
+    // Three inserts overflow the two-slot scratch; clear falls back.
+    for value in [1, 14, 6] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert!(queue.scratch.as_ref().unwrap().overflowed);
+    queue.clear();
+    assert!(!queue.contains(1) && !queue.contains(14) && !queue.contains(6));
+    assert!(!queue.scratch.as_ref().unwrap().overflowed);
+
+    assert_eq!(queue.push(6), Ok(PushResult::Inserted));
+    queue.clear();
+    assert!(!queue.contains(6));
+  }
+
+  #[test]
+  fn pop_last_aware_flags_only_final_pop() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    for value in [2, 4, 6] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    assert_eq!(queue.pop_last_aware(), Some((2, false)));
+    assert_eq!(queue.pop_last_aware(), Some((4, false)));
+    assert_eq!(queue.pop_last_aware(), Some((6, true)));
+    assert_eq!(queue.pop_last_aware(), None);
+  }
+
+  #[test]
+  fn distribute_into_round_robins_across_destinations() {
+    let mut buf = [0usize; 8];
+    let mut membership = [false; 16];
+    let mut source = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    for value in 1..=7 {
+      assert_eq!(source.push(value), Ok(PushResult::Inserted));
+    }
+
+    let (mut buf_a, mut buf_b, mut buf_c) = ([0usize; 2], [0usize; 2], [0; 4]);
+    let (mut mem_a, mut mem_b, mut mem_c) =
+      ([false; 16], [false; 16], [false; 16]);
+    let mode = MembershipMode::InQueue;
+    let order = ProcessingOrder::Fifo;
+    let mut a = TinySetQueue::new(&mut buf_a, &mut mem_a, mode, order);
+    let mut b = TinySetQueue::new(&mut buf_b, &mut mem_b, mode, order);
+    let mut c = TinySetQueue::new(&mut buf_c, &mut mem_c, mode, order);
+    assert_eq!(c.push(4), Ok(PushResult::Inserted));
+
+    let distributed = source.distribute_into(&mut [&mut a, &mut b, &mut c]);
+
+    // 1->a, 2->b, 3->c, 4->a, 5->b, 6->c, 7->c (a and b full).
+    assert_eq!(distributed, 7);
+    assert!(source.is_empty());
+    assert_eq!(a.iter().copied().sum::<usize>(), 1 + 4);
+    assert_eq!(b.iter().copied().sum::<usize>(), 2 + 5);
+    assert_eq!(c.iter().copied().sum::<usize>(), 4 + 3 + 6 + 7);
+    a.clear();
+    b.clear();
+    c.clear();
+  }
+
+  #[test]
+  fn distribute_into_keeps_value_no_destination_accepts() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 16];
+    let mut source = TinySetQueue::new(
+      &mut buf,
+      &mut membership[..],
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(source.push(12), Ok(PushResult::Inserted));
+    assert_eq!(source.push(1), Ok(PushResult::Inserted));
+
+    let mut dest_buf = [0usize; 4];
+    let mut dest_membership = [false; 8];
+    let mut dest = TinySetQueue::new(
+      &mut dest_buf,
+      &mut dest_membership[..],
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(source.distribute_into(&mut [&mut dest]), 0);
+    assert_eq!(source.len(), 2);
+    assert_eq!(source.pop(), Some(12));
+    source.clear();
+  }
+
+  #[test]
+  fn retain_compacts_wrapped_ring_in_one_pass() {
+    type Check = fn(&usize) -> bool;
+    let cases: [(Check, [usize; 4], usize); 3] = [
+      (|v| v % 2 == 0, [4, 6, 0, 0], 2),
+      (|_| true, [3, 4, 5, 6], 4),
+      (|_| false, [0; 4], 0),
+    ];
+    for (keep, expected, expected_len) in cases {
+      let mut buf = [0usize; 4];
+      let mut membership = [false; 8];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        MembershipMode::InQueue,
+        ProcessingOrder::Fifo,
+      );
+      for value in [1, 2, 3] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+      queue.pop();
+      queue.pop();
+      for value in [4, 5, 6] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+
+      queue.retain(keep);
+
+      assert_eq!(queue.len(), expected_len);
+      for (slot, value) in expected.iter().zip(queue.iter()) {
+        assert_eq!(slot, value);
+      }
+      for value in 3..=6 {
+        assert_eq!(
+          queue.contains(value),
+          expected[..expected_len].contains(&value)
+        );
+      }
+      // The ring keeps working after compaction.
+      if !queue.is_full() {
+        assert_eq!(queue.push(7), Ok(PushResult::Inserted));
+        assert_eq!(queue.iter().next_back(), Some(&7));
+      }
+      queue.clear();
+    }
+  }
+
+  #[test]
+  fn retain_clears_visited_membership_of_dropped_values() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Lifo,
+    );
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    queue.retain(|&v| v != 2);
+
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.pop(), Some(1));
+    assert!(!queue.contains(2));
+    assert!(queue.contains(1) && queue.contains(3));
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+  }
+
+  #[test]
+  fn into_parts_full_round_trips_wrapped_ring() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    queue.pop();
+    queue.pop();
+    for value in [4, 5, 6] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    let parts = queue.into_parts_full();
+    assert_eq!((parts.head, parts.tail, parts.len), (2, 2, 4));
+    let mut queue = TinySetQueue::from_parts_full(parts).unwrap();
+
+    assert_eq!(queue.push(1), Ok(PushResult::AlreadyPresent));
+    for expected in [3, 4, 5, 6] {
+      assert_eq!(queue.pop(), Some(expected));
+    }
+    assert_eq!(queue.pop(), None);
+    assert!(queue.contains(2));
+
+    let (buf, membership) = queue.into_parts();
+    assert_eq!(buf.len(), 4);
+    assert!(membership[6] && !membership[7]);
+  }
+
+  #[test]
+  fn from_parts_full_rejects_inconsistent_indices() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    let mut parts = queue.into_parts_full();
+    parts.len = 3;
+    assert!(matches!(
+      TinySetQueue::from_parts_full(parts),
+      Err("len does not match the distance from head to tail")
+    ));
+  }
+
+  #[test]
+  fn members_count_tracks_membership_changes() {
+    let mut buf = [0usize; 4];
+    let mut membership = [0u64; 2];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.members_count(), 0);
+
+    for value in [1, 70, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.members_count(), 3);
+    #[cfg(feature = "members_cache")]
+    assert_eq!(queue.members_count_cached(), 3);
+
+    assert_eq!(queue.push(4), Ok(PushResult::Inserted));
+    #[cfg(feature = "members_cache")]
+    assert_eq!(queue.members_count_cached(), 4);
+    queue.pop();
+    assert_eq!(queue.members_count(), 4);
+
+    queue.clear();
+    #[cfg(feature = "members_cache")]
+    assert_eq!(queue.members_count_cached(), 0);
+    assert_eq!(queue.members_count(), 0);
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Lifo,
+    );
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(6), Ok(PushResult::Inserted));
+    assert_eq!(queue.members_count(), 2);
+    #[cfg(feature = "members_cache")]
+    assert_eq!(queue.members_count_cached(), 2);
+    assert_eq!(queue.pop(), Some(6));
+    assert_eq!(queue.members_count(), 1);
+    #[cfg(feature = "members_cache")]
+    assert_eq!(queue.members_count_cached(), 1);
+    queue.clear();
+  }
+
+  #[test]
+  fn queue_is_sync_for_sync_backings() {
+    fn assert_sync<Q: Sync>() {}
+    assert_sync::<TinySetQueue<'static, usize, [bool]>>();
+    assert_sync::<TinySetQueue<'static, u16, [u64]>>();
+  }
+
+  #[test]
+  fn push_if_in_range_distinguishes_outcomes() {
+    let mut buf = [0usize; 1];
+    let mut membership = [false; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.push_if_in_range(9), PushOutcome::OutOfRange);
+    assert_eq!(
+      queue.push_if_in_range(2),
+      PushOutcome::Pushed(PushResult::Inserted)
+    );
+    assert_eq!(
+      queue.push_if_in_range(2),
+      PushOutcome::Pushed(PushResult::AlreadyPresent)
+    );
+    assert_eq!(queue.push_if_in_range(3), PushOutcome::Full);
+    assert_eq!(queue.push_if_in_range(4), PushOutcome::OutOfRange);
+    queue.clear();
+  }
+
+  #[test]
+  fn capture_then_restore_preserves_pop_sequence() {
+    for order in [ProcessingOrder::Fifo, ProcessingOrder::Lifo] {
+      let mut buf = [0usize; 4];
+      let mut membership = [false; 8];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        MembershipMode::Visited,
+        order,
+      );
+      for value in [1, 2, 3] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+      let first = queue.pop().unwrap();
+      let second = queue.pop().unwrap();
+      for value in [4, 5, 6] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+
+      let mut saved_buf = [0usize; 4];
+      let mut saved_membership = [false; 8];
+      let captured = queue
+        .capture(&mut saved_buf, &mut saved_membership)
+        .unwrap();
+      let mut expected = [0usize; 4];
+      for slot in expected.iter_mut() {
+        *slot = queue.pop().unwrap();
+      }
+      assert_eq!(queue.push(7), Ok(PushResult::Inserted));
+
+      queue
+        .restore(&captured, &saved_buf, &saved_membership)
+        .unwrap();
+      assert_eq!(queue.len(), 4);
+      for value in expected {
+        assert_eq!(queue.pop(), Some(value));
+      }
+      assert!(queue.contains(first) && queue.contains(second));
+      assert!(!queue.contains(7));
+    }
+  }
+
+  #[test]
+  fn capture_rejects_short_buffer() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf[..],
+      &mut membership[..],
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+
+    let mut short = [0usize; 1];
+    let mut saved_membership = [false; 8];
+    assert_eq!(
+      queue.capture(&mut short, &mut saved_membership[..]),
+      Err(CaptureError::BufferTooSmall)
+    );
+    let mut small_membership = [false; 4];
+    let mut saved_buf = [0usize; 2];
+    assert_eq!(
+      queue.capture(&mut saved_buf, &mut small_membership[..]),
+      Err(CaptureError::MembershipTooSmall)
+    );
+    queue.clear();
+  }
+
+  #[test]
+  fn insertion_min_pops_smallest_key_first() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::InsertionMin,
+    );
+    for value in [9, 3, 12] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(3));
+    assert!(!queue.contains(3));
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(7), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(9), Ok(PushResult::AlreadyPresent));
+
+    // The ring has wrapped; iteration stays in insertion order.
+    let mut seen = [0usize; 4];
+    for (slot, value) in seen.iter_mut().zip(queue.iter()) {
+      *slot = *value;
+    }
+    assert_eq!(seen, [9, 12, 1, 7]);
+    assert_eq!(queue.peek(), Some(&1));
+
+    for expected in [1, 7, 9, 12] {
+      assert_eq!(queue.pop(), Some(expected));
+      assert!(!queue.contains(expected));
+    }
+    assert_eq!(queue.pop(), None);
+  }
+
+  #[test]
+  fn insertion_min_keeps_visited_membership() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::InsertionMin,
+    );
+    for value in [5, 2, 6] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(2));
+    assert_eq!(queue.push(2), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.push(4), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(4));
+    assert_eq!(queue.pop(), Some(5));
+    assert_eq!(queue.pop(), Some(6));
+    assert!(queue.contains(4));
+  }
+
+  #[test]
+  fn members_density_reports_marked_fraction() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.members_density(), 0.0);
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(6), Ok(PushResult::Inserted));
+    assert_eq!(queue.members_density(), 0.25);
+    queue.clear();
+
+    let mut buf = [0usize; 1];
+    let mut membership: [u64; 0] = [];
+    let queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.members_density(), 0.0);
+  }
+
+  #[test]
+  fn drain_grouped_delivers_each_value_with_its_class() {
+    let mut buf = [0usize; 8];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    for value in [3, 8, 4, 11, 6, 9] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    let mut buckets = [[0usize; 6]; 3];
+    let mut counts = [0usize; 3];
+    queue.drain_grouped(
+      |value| value % 3,
+      |class, value| {
+        buckets[class][counts[class]] = value;
+        counts[class] += 1;
+      },
+    );
+
+    assert!(queue.is_empty());
+    assert!(!queue.contains(8));
+    assert_eq!(counts, [3, 1, 2]);
+    assert_eq!(buckets[0][..3], [3, 6, 9]);
+    assert_eq!(buckets[1][..1], [4]);
+    assert_eq!(buckets[2][..2], [8, 11]);
+  }
+
+  #[test]
+  fn high_water_tracks_maximum_inserted_index() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    #[cfg(feature = "clear_on_new")]
+    assert_eq!(queue.high_water(), 0);
+    #[cfg(not(feature = "clear_on_new"))]
+    assert_eq!(queue.high_water(), 16);
+    queue.reset_high_water();
+
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(11), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.high_water(), 12);
+    assert_eq!(queue.pop(), Some(5));
+    assert_eq!(queue.high_water(), 12);
+
+    queue.reset_high_water();
+    assert_eq!(queue.high_water(), 0);
+    assert_eq!(queue.push(7), Ok(PushResult::Inserted));
+    assert_eq!(queue.high_water(), 8);
+    queue.clear();
+    assert_eq!(queue.high_water(), 0);
+  }
+
+  #[test]
+  fn swap_remove_key_moves_newest_into_gap() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(1));
+    for value in [4, 5] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    assert_eq!(queue.swap_remove_key(3), Some(3));
+    assert_eq!(queue.swap_remove_key(3), None);
+    assert_eq!(queue.swap_remove_key(1), None);
+    assert_eq!(queue.swap_remove_key(9), None);
+    assert!(!queue.contains(3));
+
+    let mut seen = [0usize; 3];
+    for (slot, value) in seen.iter_mut().zip(queue.iter()) {
+      *slot = *value;
+    }
+    assert_eq!(seen, [2, 5, 4]);
+    assert_eq!(queue.push(3), Ok(PushResult::Inserted));
+    assert_eq!(queue.len(), 4);
+  }
+
+  #[cfg(feature = "rayon")]
+  #[test]
+  fn par_members_yields_every_marked_index() {
+    use rayon::iter::ParallelIterator;
+
+    let mut buf = [0usize; 8];
+    let mut membership = [0u64; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    for value in [0, 63, 64, 130, 255, 7] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    queue.pop();
+
+    let mut members: std::vec::Vec<usize> = queue.par_members().collect();
+    members.sort_unstable();
+    assert_eq!(members, [0, 7, 63, 64, 130, 255]);
+  }
+
+  #[test]
+  fn trim_front_stops_at_first_fresh_value() {
+    let mut buf = [0usize; 8];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    for value in [1, 2, 3, 10, 4, 11] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    assert_eq!(queue.trim_front(|&v| v < 5), 3);
+    assert!(!queue.contains(2));
+    assert_eq!(queue.peek(), Some(&10));
+    assert_eq!(queue.len(), 3);
+    assert_eq!(queue.trim_front(|&v| v < 5), 0);
+    assert_eq!(queue.trim_front(|_| true), 3);
+    assert!(queue.is_empty());
+  }
+
+  #[test]
+  fn members_xor_into_computes_symmetric_difference() {
+    let mut buf = [0usize; 4];
+    let mut membership = [0u64; 1];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    for value in [1, 5, 40] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    let mut out = [0u64; 2];
+    queue.members_xor_into(&[(1u64 << 1) | (1 << 5) | (1 << 40)], &mut out);
+    assert_eq!(out, [0, 0]);
+
+    queue.members_xor_into(&[1u64 << 2, 1 << 3], &mut out);
+    assert_eq!(out, [(1 << 1) | (1 << 2) | (1 << 5) | (1 << 40), 1 << 3]);
+
+    let mut other = [false; 8];
+    other[5] = true;
+    other[6] = true;
+    let mut bool_out = [false; 64];
+    queue.members_xor_into(&other, &mut bool_out);
+    for (index, &present) in bool_out.iter().enumerate() {
+      assert_eq!(present, matches!(index, 1 | 6 | 40));
+    }
+  }
+
+  #[test]
+  fn push_at_reports_landing_slot() {
+    let mut buf = [0usize; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.push_at(4), Ok((PushResult::Inserted, Some(0))));
+    assert_eq!(queue.push_at(5), Ok((PushResult::Inserted, Some(1))));
+    assert_eq!(queue.pop(), Some(4));
+    assert_eq!(queue.push_at(6), Ok((PushResult::Inserted, Some(2))));
+    // The ring wraps back to slot 0.
+    assert_eq!(queue.push_at(7), Ok((PushResult::Inserted, Some(0))));
+    assert_eq!(queue.push_at(6), Ok((PushResult::AlreadyPresent, Some(2))));
+    assert_eq!(queue.push_at(4), Ok((PushResult::AlreadyPresent, None)));
+    assert_eq!(queue.push_at(1), Err(PushError::Full(1)));
+
+    let (buf, _) = queue.into_parts();
+    assert_eq!(buf, [7, 5, 6]);
+  }
+
+  #[test]
+  fn estimate_bytes_matches_constructed_storage() {
+    use super::{estimate_bytes, BackingKind};
+    use core::mem::size_of_val;
+
+    const PLANNED: usize = estimate_bytes::<u16>(12, 100, BackingKind::U64);
+    let buf = [0u16; 12];
+    let membership = [0u64; 2];
+    assert_eq!(PLANNED, size_of_val(&buf) + size_of_val(&membership));
+
+    let flags = [false; 100];
+    assert_eq!(
+      estimate_bytes::<u16>(12, 100, BackingKind::Bool),
+      size_of_val(&buf) + size_of_val(&flags)
+    );
+    assert_eq!(estimate_bytes::<u32>(0, 33, BackingKind::U32), 8);
+    let bytes = [0u8; 13];
+    assert_eq!(
+      estimate_bytes::<u16>(12, 100, BackingKind::U8),
+      size_of_val(&buf) + size_of_val(&bytes)
+    );
+    assert_eq!(estimate_bytes::<u8>(3, 16, BackingKind::U8), 5);
+    assert_eq!(estimate_bytes::<u64>(2, 0, BackingKind::U64), 16);
+  }
+
+  #[test]
+  fn drain_into_sorted_writes_sorted_prefix() {
+    let mut buf = [0usize; 8];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    for value in [9, 2, 14, 5, 0] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    let mut out = [99usize; 3];
+    assert_eq!(queue.drain_into_sorted(&mut out), 3);
+    assert_eq!(out, [2, 9, 14]);
+    assert_eq!(queue.len(), 2);
+    assert!(!queue.contains(9));
+
+    let mut out = [99usize; 4];
+    assert_eq!(queue.drain_into_sorted(&mut out), 2);
+    assert_eq!(out, [0, 5, 99, 99]);
+    assert!(queue.is_empty());
+  }
+
+  #[test]
+  fn new_with_config_matches_new() {
+    use super::QueueConfig;
+
+    const BFS: QueueConfig = QueueConfig::bfs();
+    let presets = [
+      (BFS, MembershipMode::Visited, ProcessingOrder::Fifo),
+      (
+        QueueConfig::dfs(),
+        MembershipMode::Visited,
+        ProcessingOrder::Lifo,
+      ),
+      (
+        QueueConfig::new(MembershipMode::InQueue, ProcessingOrder::Fifo),
+        MembershipMode::InQueue,
+        ProcessingOrder::Fifo,
+      ),
+    ];
+    for (config, mode, order) in presets {
+      let mut buf = [0usize; 4];
+      let mut membership = [true; 8];
+      let mut configured =
+        TinySetQueue::new_with_config(&mut buf, &mut membership, config);
+      let mut other_buf = [0usize; 4];
+      let mut other_membership = [false; 8];
+      let mut plain =
+        TinySetQueue::new(&mut other_buf, &mut other_membership, mode, order);
+
+      assert_eq!(
+        (configured.mode, configured.order),
+        (plain.mode, plain.order)
+      );
+      assert_eq!(configured.high_water(), 0);
+      for value in [3, 1, 2] {
+        assert_eq!(configured.push(value), plain.push(value));
+      }
+      for _ in 0..3 {
+        assert_eq!(configured.pop(), plain.pop());
+      }
+      assert_eq!(configured.push(1), plain.push(1));
+      configured.clear();
+      plain.clear();
+    }
+  }
+
+  #[test]
+  fn new_with_config_can_keep_existing_membership() {
+    use super::QueueConfig;
+
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    membership[5] = true;
+    let config = QueueConfig {
+      clear_on_new: false,
+      ..QueueConfig::bfs()
+    };
+    let mut queue =
+      TinySetQueue::new_with_config(&mut buf, &mut membership, config);
+    assert_eq!(queue.push(5), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.high_water(), 8);
+  }
+
+  #[test]
+  fn from_parts_validated_rejects_membership_desync() {
+    use super::{QueueParts, StateError};
+
+    let mut buf = [4usize, 6, 0, 0];
+    let mut membership = [false; 8];
+    membership[4] = true;
+    membership[6] = true;
+    fn parts<'b>(
+      buf: &'b mut [usize],
+      in_queue: &'b mut [bool; 8],
+      mode: MembershipMode,
+    ) -> QueueParts<'b, usize, [bool; 8]> {
+      QueueParts {
+        buf,
+        in_queue,
+        head: 0,
+        tail: 2,
+        len: 2,
+        mode,
+        order: ProcessingOrder::Fifo,
+      }
+    }
+
+    let queue = TinySetQueue::from_parts_validated(parts(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+    ))
+    .unwrap();
+    let (buf, membership) = queue.into_parts();
+
+    membership[6] = false;
+    membership[1] = true;
+    let Err(error) = TinySetQueue::from_parts_validated(parts(
+      &mut *buf,
+      &mut *membership,
+      MembershipMode::InQueue,
+    )) else {
+      panic!("desynced membership was accepted");
+    };
+    assert_eq!(
+      error,
+      StateError::Unmarked {
+        offset: 1,
+        index: 6
+      }
+    );
+
+    membership[6] = true;
+    let Err(error) = TinySetQueue::from_parts_validated(parts(
+      &mut *buf,
+      &mut *membership,
+      MembershipMode::InQueue,
+    )) else {
+      panic!("stray membership was accepted");
+    };
+    assert_eq!(error, StateError::ExtraMember { index: 1 });
+
+    // Visited mode keeps flags for values already popped.
+    let mut queue = TinySetQueue::from_parts_validated(parts(
+      &mut *buf,
+      &mut *membership,
+      MembershipMode::Visited,
+    ))
+    .unwrap();
+    assert_eq!(queue.pop(), Some(4));
+
+    let (buf, membership) = queue.into_parts();
+    buf[1] = 9;
+    let Err(error) = TinySetQueue::from_parts_validated(parts(
+      buf,
+      membership,
+      MembershipMode::Visited,
+    )) else {
+      panic!("out-of-range value was accepted");
+    };
+    assert_eq!(
+      error,
+      StateError::OutOfRange {
+        offset: 1,
+        index: 9
+      }
+    );
+  }
+
+  #[test]
+  fn peek_matches_next_pop_without_mutation() {
+    for order in [ProcessingOrder::Fifo, ProcessingOrder::Lifo] {
+      let mut buf = [0usize; 3];
+      let mut membership = [false; 8];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        MembershipMode::InQueue,
+        order,
+      );
+      assert_eq!(TinySetQueue::peek(&queue), None);
+      for value in [1, 2, 3] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+      queue.pop();
+      // The next push lands in slot 0, so LIFO must wrap `tail - 1`.
+      assert_eq!(queue.push(4), Ok(PushResult::Inserted));
+
+      while let Some(&next) = TinySetQueue::peek(&queue) {
+        let len = queue.len();
+        assert_eq!(TinySetQueue::peek(&queue), Some(&next));
+        assert_eq!(queue.len(), len);
+        assert!(queue.contains(next));
+        assert_eq!(queue.pop(), Some(next));
+      }
+    }
+  }
+
+  #[test]
+  fn push_unless_excluded_skips_forbidden_keys() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    let forbidden = [(1u64 << 3) | (1 << 7)];
+
+    assert_eq!(
+      queue.push_unless_excluded(3, &forbidden),
+      Ok(PushResult::Excluded)
+    );
+    assert!(!queue.contains(3));
+    assert_eq!(
+      queue.push_unless_excluded(4, &forbidden),
+      Ok(PushResult::Inserted)
+    );
+    assert_eq!(
+      queue.push_unless_excluded(4, &forbidden),
+      Ok(PushResult::AlreadyPresent)
+    );
+    assert_eq!(
+      queue.push_unless_excluded(20, &forbidden),
+      Err(PushError::OutOfRange(20))
+    );
+    assert_eq!(
+      queue.push_unless_excluded(7, &[false; 4][..]),
+      Ok(PushResult::Inserted)
+    );
+    assert_eq!(queue.len(), 2);
+  }
+
+  #[test]
+  fn u32_backing_matches_u64_backing() {
+    use super::SetBacking;
+
+    fn exercise<S: SetBacking + ?Sized>(membership: &mut S) -> [bool; 64] {
+      membership.clear_all();
+      let mut buf = [0usize; 8];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        membership,
+        MembershipMode::Visited,
+        ProcessingOrder::Fifo,
+      );
+      for value in [0, 31, 32, 33, 63, 5] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+      assert_eq!(queue.push(64), Err(PushError::OutOfRange(64)));
+      assert_eq!(queue.pop(), Some(0));
+      assert_eq!(queue.push(31), Ok(PushResult::AlreadyPresent));
+      let mut seen = [false; 64];
+      queue.members_for_each(|index| seen[index] = true);
+      seen
+    }
+
+    let mut words32 = [0u32; 2];
+    let mut words64 = [0u64; 1];
+    let expected = exercise(&mut words64);
+    assert_eq!(exercise(&mut words32), expected);
+    assert_eq!(exercise(&mut words32[..]), expected);
+    assert_eq!(words32, [(1 << 31) | (1 << 5) | 1, (1 << 31) | 0b11]);
+
+    words32.remove(32);
+    assert!(!words32.contains(32) && words32.contains(33));
+    words32.clear_up_to(33);
+    assert_eq!(words32, [0, 0]);
+    assert_eq!(words32.capacity(), 64);
+  }
+
+  #[test]
+  fn u128_backing_matches_u64_backing() {
+    use super::SetBacking;
+
+    fn exercise<S: SetBacking + ?Sized>(membership: &mut S) -> [bool; 256] {
+      membership.clear_all();
+      let mut buf = [0usize; 8];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        membership,
+        MembershipMode::Visited,
+        ProcessingOrder::Fifo,
+      );
+      for value in [0, 63, 64, 127, 128, 200, 255] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+      assert_eq!(queue.push(256), Err(PushError::OutOfRange(256)));
+      assert_eq!(queue.pop(), Some(0));
+      assert_eq!(queue.push(127), Ok(PushResult::AlreadyPresent));
+      let mut seen = [false; 256];
+      queue.members_for_each(|index| seen[index] = true);
+      seen
+    }
+
+    let mut words128 = [0u128; 2];
+    let mut words64 = [0u64; 4];
+    let expected = exercise(&mut words64);
+    assert_eq!(exercise(&mut words128), expected);
+    assert_eq!(exercise(&mut words128[..]), expected);
+    let mut bytes = [0u8; 32];
+    assert_eq!(exercise(&mut bytes), expected);
+    assert_eq!(exercise(&mut bytes[..]), expected);
+    assert_eq!(bytes.count(), 7);
+    assert_eq!(bytes[..].count(), 7);
+    bytes.remove(63);
+    assert!(!bytes.contains(63) && bytes.contains(64));
+    bytes.clear_up_to(65);
+    assert_eq!(bytes[..9], [0; 9]);
+    assert_eq!(bytes.capacity(), 256);
+    assert_eq!(
+      words128,
+      [
+        1 | (1 << 63) | (1 << 64) | (1 << 127),
+        1 | (1 << 72) | (1 << 127)
+      ]
+    );
+    assert_eq!(words128.count(), 7);
+
+    words128.remove(127);
+    assert!(!words128.contains(127) && words128.contains(128));
+    words128.clear_up_to(129);
+    assert_eq!(words128, [0, 0]);
+    assert_eq!(words128.capacity(), 256);
+  }
+
+  #[test]
+  fn members_rle_round_trips_clustered_set() {
+    let mut buf = [0usize; 1];
+    let mut membership = [0u64; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    let runs = [(3, 4), (60, 140), (210, 1), (255, 1)];
+    queue.set_members_from_rle(&runs);
+    assert_eq!(queue.members_count(), 146);
+
+    let mut encoded = [(0, 0); 4];
+    assert_eq!(queue.members_rle(&mut encoded), 4);
+    assert_eq!(encoded, runs);
+
+    let mut short = [(0, 0); 2];
+    assert_eq!(queue.members_rle(&mut short), 4);
+    assert_eq!(short, [(3, 4), (60, 140)]);
+
+    let mut other_buf = [0usize; 1];
+    let mut other_membership = [0u64; 4];
+    let mut restored = TinySetQueue::new(
+      &mut other_buf,
+      &mut other_membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    restored.set_members_from_rle(&encoded);
+    let (_, restored_membership) = restored.into_parts();
+    let (_, membership) = queue.into_parts();
+    assert_eq!(restored_membership, membership);
+  }
+
+  #[test]
+  fn members_rle_handles_bool_backing() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 10];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    for value in [9, 1, 2, 0] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    let mut encoded = [(0, 0); 3];
+    assert_eq!(queue.members_rle(&mut encoded), 2);
+    assert_eq!(encoded[..2], [(0, 3), (9, 1)]);
+  }
+
+  #[test]
+  fn set_backing_count_matches_marked_indices() {
+    use super::SetBacking;
+
+    let mut flags = [false; 70];
+    let mut words64 = [0u64; 2];
+    let mut words32 = [0u32; 3];
+    for index in [0, 3, 31, 32, 63, 64, 69] {
+      flags.insert(index);
+      words64.insert(index);
+      words32.insert(index);
+    }
+    assert_eq!(flags.count(), 7);
+    assert_eq!(flags[..].count(), 7);
+    assert_eq!(words64.count(), 7);
+    assert_eq!(words64[..].count(), 7);
+    assert_eq!(words32.count(), 7);
+    assert_eq!(words32[..].count(), 7);
+  }
+
+  #[test]
+  fn visited_count_includes_popped_indices() {
+    let mut buf = [0usize; 4];
+    let mut membership = [0u32; 1];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Lifo,
+    );
+    assert_eq!(queue.visited_count(), 0);
+    for value in [1, 9, 17] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    while queue.pop().is_some() {}
+    assert_eq!(queue.push(9), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.visited_count(), 3);
+  }
+
+  #[test]
+  fn iter_is_exact_size_and_borrows_immutably() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    queue.pop();
+    for value in [4, 5] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    let mut iter = queue.iter();
+    assert_eq!(iter.len(), 4);
+    assert_eq!(iter.size_hint(), (4, Some(4)));
+    // Holding the iterator still allows shared access to the queue.
+    assert_eq!(TinySetQueue::peek(&queue), iter.next());
+    assert_eq!(iter.len(), queue.len() - 1);
+    assert_eq!(iter.by_ref().count(), 3);
+    assert_eq!(iter.len(), 0);
+    assert_eq!(iter.next(), None);
+    assert!(queue.contains(5));
+  }
+
+  #[test]
+  fn key_at_slot_tracks_pending_window() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    assert!((0..5).all(|slot| queue.key_at_slot(slot).is_none()));
+
+    for value in [10, 11, 12, 13] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.key_at_slot(0), Some(10));
+    assert_eq!(queue.key_at_slot(3), Some(13));
+    assert_eq!(queue.key_at_slot(4), None);
+
+    queue.pop();
+    queue.pop();
+    queue.pop();
+    assert_eq!(queue.push_at(14), Ok((PushResult::Inserted, Some(0))));
+    // Pending window wraps: slots 3, 0.
+    assert_eq!(queue.key_at_slot(3), Some(13));
+    assert_eq!(queue.key_at_slot(0), Some(14));
+    assert_eq!(queue.key_at_slot(1), None);
+    assert_eq!(queue.key_at_slot(2), None);
+    queue.clear();
+  }
+
+  #[test]
+  fn drain_budget_processes_at_most_budget() {
+    let mut buf = [0usize; 8];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    for value in [5, 6, 7, 8, 9] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    let mut sum = 0;
+    assert_eq!(queue.drain_budget(2, |value| sum += value), 2);
+    assert_eq!(sum, 11);
+    assert!(!queue.contains(6));
+    assert_eq!(queue.peek(), Some(&7));
+
+    assert_eq!(queue.drain_budget(0, |_| unreachable!()), 0);
+    assert_eq!(queue.drain_budget(10, |value| sum += value), 3);
+    assert_eq!(sum, 35);
+    assert!(queue.is_empty());
+  }
+
+  #[test]
+  fn try_push_all_stops_at_first_rejection() {
+    let mut buf = [0usize; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+
+    assert_eq!(queue.try_push_all(&[1, 2, 1, 9, 3]), 3);
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.try_push_all(&[3, 2, 4, 5]), 2);
+    assert_eq!(queue.len(), 3);
+    assert_eq!(queue.try_push_all(&[]), 0);
+    queue.clear();
+  }
+
+  #[test]
+  fn ensure_queued_distinguishes_queued_from_visited() {
+    use super::EnsureResult;
+
+    for mode in [MembershipMode::InQueue, MembershipMode::Visited] {
+      let mut buf = [0usize; 2];
+      let mut membership = [false; 8];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        mode,
+        ProcessingOrder::Fifo,
+      );
+      assert_eq!(queue.ensure_queued(1), Ok(EnsureResult::NewlyQueued));
+      assert_eq!(queue.ensure_queued(2), Ok(EnsureResult::NewlyQueued));
+      assert_eq!(queue.ensure_queued(2), Ok(EnsureResult::AlreadyQueued));
+      assert_eq!(queue.ensure_queued(3), Err(PushError::Full(3)));
+      assert_eq!(queue.ensure_queued(8), Err(PushError::OutOfRange(8)));
+
+      assert_eq!(queue.pop(), Some(1));
+      let expected = match mode {
+        MembershipMode::InQueue => EnsureResult::NewlyQueued,
+        MembershipMode::Visited => EnsureResult::AlreadyVisited,
+      };
+      assert_eq!(queue.ensure_queued(1), Ok(expected));
+      queue.clear();
+    }
+  }
+
+  #[test]
+  fn make_contiguous_reporting_detects_wrap() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(1));
+    assert!(!queue.make_contiguous_reporting());
+    assert_eq!(queue.make_contiguous(), &[2, 3]);
+
+    for value in [4, 5] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(2));
+    assert_eq!(queue.push(6), Ok(PushResult::Inserted));
+    assert!(queue.make_contiguous_reporting());
+    assert!(!queue.make_contiguous_reporting());
+    for value in [3, 4, 5, 6] {
+      assert_eq!(queue.pop(), Some(value));
+    }
+  }
+
+  #[test]
+  fn contains_reports_visited_values_after_pop() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    assert!(!queue.contains(3));
+    if !queue.contains(3) {
+      assert_eq!(queue.push(3), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(3));
+    assert!(queue.is_empty());
+    assert!(queue.contains(3));
+    assert!(!queue.contains(200));
+  }
+
+  #[test]
+  fn partition_point_by_key_handles_wrapped_ring() {
+    let mut buf = [0usize; 5];
+    let mut membership = [false; 32];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.partition_point_by_key(|key| key < 10), 0);
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), Some(2));
+    // Pending keys [3, 5, 8, 13, 21] start at slot 2 and wrap to slot 0.
+    for value in [5, 8, 13, 21] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    assert_eq!(queue.partition_point_by_key(|key| key < 3), 0);
+    assert_eq!(queue.partition_point_by_key(|key| key < 4), 1);
+    assert_eq!(queue.partition_point_by_key(|key| key <= 8), 3);
+    assert_eq!(queue.partition_point_by_key(|key| key < 20), 4);
+    assert_eq!(queue.partition_point_by_key(|key| key < 30), 5);
+  }
+
+  #[test]
+  fn drain_empties_queue_even_when_dropped_early() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Lifo,
+    );
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+
+    let mut drain = queue.drain();
+    assert_eq!(drain.len(), 3);
+    assert_eq!(drain.next(), Some(3));
+    assert_eq!(drain.len(), 2);
+    drop(drain);
+    assert!(queue.is_empty());
+    assert!(queue.contains(1) && queue.contains(2) && queue.contains(3));
+
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Lifo,
+    );
+    for value in [4, 5] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    let mut drained = [0usize; 2];
+    for (slot, value) in drained.iter_mut().zip(queue.drain()) {
+      *slot = value;
+    }
+    assert_eq!(drained, [5, 4]);
+    assert!(!queue.contains(4) && !queue.contains(5));
+    assert_eq!(queue.drain().next(), None);
+  }
+
+  #[test]
+  fn init_with_leaves_no_partial_fill_on_failure() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let Err((queue, rejected)) = TinySetQueue::init_with(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+      &[1, 2, 9, 3],
+    ) else {
+      panic!("out-of-range seed was accepted");
+    };
+    assert_eq!(rejected, 9);
+    assert!(queue.is_empty());
+    assert!(!queue.contains(1) && !queue.contains(2));
+
+    let (buf, membership) = queue.into_parts();
+    let Ok(mut queue) = TinySetQueue::init_with(
+      buf,
+      membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+      &[3, 1, 3],
+    ) else {
+      panic!("valid seed was rejected");
+    };
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.pop(), Some(1));
+  }
+
+  #[test]
+  fn members_word_counts_sum_to_members_count() {
+    let mut buf = [0usize; 8];
+    let mut membership = [0u64; 3];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    for value in [0, 1, 2, 63, 130, 150] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    while queue.pop().is_some() {}
+
+    let mut counts = [0xffu8; 4];
+    assert_eq!(queue.members_word_counts(&mut counts), 3);
+    assert_eq!(counts, [4, 0, 2, 0xff]);
+    let total: usize = counts[..3].iter().map(|&count| count as usize).sum();
+    assert_eq!(total, queue.members_count());
+
+    let mut short = [0u8; 1];
+    assert_eq!(queue.members_word_counts(&mut short), 1);
+    assert_eq!(short, [4]);
+  }
+
+  #[test]
+  fn remove_closes_gap_in_wrapped_ring() {
+    for order in [ProcessingOrder::Fifo, ProcessingOrder::Lifo] {
+      let mut buf = [0usize; 4];
+      let mut membership = [false; 8];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        MembershipMode::Visited,
+        order,
+      );
+      for value in [1, 2, 3] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+      let popped = queue.pop().unwrap();
+      // Under FIFO the pending values now wrap past the end of the ring.
+      for value in [4, 5] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+
+      assert!(queue.remove(4));
+      assert!(!queue.remove(4));
+      assert!(!queue.remove(popped));
+      assert!(!queue.remove(7));
+      assert!(!queue.contains(4));
+      assert_eq!(queue.len(), 3);
+
+      assert_eq!(queue.push(6), Ok(PushResult::Inserted));
+      let mut seen = [0usize; 4];
+      for (slot, value) in seen.iter_mut().zip(queue.drain()) {
+        *slot = value;
+      }
+      match order {
+        ProcessingOrder::Lifo => assert_eq!(seen, [6, 5, 2, 1]),
+        _ => assert_eq!(seen, [2, 3, 5, 6]),
+      }
+    }
+  }
+
+  #[test]
+  fn recycle_applies_new_config_to_clean_queue() {
+    use super::QueueConfig;
+
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new_with_config(
+      &mut buf,
+      &mut membership,
+      QueueConfig::dfs(),
+    );
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(3));
+
+    queue.recycle(Some(QueueConfig::new(
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    )));
+    assert!(queue.is_empty());
+    assert_eq!(queue.members_count(), 0);
+    for value in [3, 1, 2] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(3));
+    assert!(!queue.contains(3));
+
+    queue.recycle(None);
+    assert!(queue.is_empty() && !queue.contains(1));
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(6), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(5));
+    assert!(!queue.contains(5));
+  }
+
+  #[test]
+  fn from_parts_resumes_wrapped_state() {
+    use super::StateError;
+
+    let mut buf = [7usize, 0, 5, 6];
+    let mut membership = [false; 8];
+    for index in [5, 6, 7] {
+      membership[index] = true;
+    }
+    let mut queue = TinySetQueue::from_parts(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+      2,
+      1,
+      3,
+    )
+    .unwrap_or_else(|error| panic!("{error}"));
+    assert_eq!(queue.pop(), Some(5));
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    let (buf, membership) = queue.into_parts();
+
+    let Err(error) = TinySetQueue::from_parts(
+      &mut *buf,
+      &mut *membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+      0,
+      0,
+      5,
+    ) else {
+      panic!("len beyond capacity was accepted");
+    };
+    assert!(matches!(error, StateError::Indices(_)));
+
+    membership[6] = false;
+    let Err(error) = TinySetQueue::from_parts(
+      buf,
+      membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+      3,
+      2,
+      3,
+    ) else {
+      panic!("unmarked value was accepted");
+    };
+    assert_eq!(
+      error,
+      StateError::Unmarked {
+        offset: 0,
+        index: 6
+      }
+    );
+  }
+
+  #[test]
+  fn from_parts_rejects_duplicate_pending_value() {
+    use super::StateError;
+
+    for mode in [MembershipMode::InQueue, MembershipMode::Visited] {
+      let mut buf = [3u8, 3, 0, 0];
+      let mut membership = [false; 8];
+      membership[3] = true;
+      let Err(error) = TinySetQueue::from_parts(
+        &mut buf,
+        &mut membership,
+        mode,
+        ProcessingOrder::Fifo,
+        0,
+        2,
+        2,
+      ) else {
+        panic!("duplicate pending value was accepted");
+      };
+      assert_eq!(
+        error,
+        StateError::Duplicate {
+          offset: 1,
+          index: 3
+        }
+      );
+      // The scratch pass leaves the caller's backing as it was.
+      assert_eq!(membership.iter().filter(|&&flag| flag).count(), 1);
+      assert!(membership[3]);
+    }
+  }
+
+  #[test]
+  fn extend_stops_at_first_rejection() {
+    let mut buf = [0usize; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    queue.extend([1, 1, 2]);
+    assert_eq!(queue.len(), 2);
+
+    let mut pulled = 0;
+    queue.extend((3..).inspect(|_| pulled += 1));
+    assert_eq!(pulled, 2);
+    assert_eq!(queue.len(), 3);
+
+    queue.clear();
+    queue.extend([6, 9, 7]);
+    assert_eq!(queue.pop(), Some(6));
+    assert_eq!(queue.pop(), None);
+  }
+
+  #[test]
+  fn set_order_reinterprets_pending_window() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Lifo,
+    );
+    for value in [1, 2, 3, 4] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(4));
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+
+    queue.set_order(ProcessingOrder::Fifo);
+    assert_eq!(queue.pop(), Some(1));
+    // Pending values now wrap around the end of the ring.
+    assert_eq!(queue.push(6), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(7), Ok(PushResult::Inserted));
+
+    queue.set_order(ProcessingOrder::Lifo);
+    assert_eq!(queue.pop(), Some(7));
+    queue.set_order(ProcessingOrder::Fifo);
+    for expected in [2, 5, 6] {
+      assert_eq!(queue.pop(), Some(expected));
+    }
+    assert_eq!(queue.pop(), None);
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn snapshot_round_trips_membership() {
+    use super::{QueueState, SetBacking};
+
+    fn assert_serde<T: serde::Serialize + serde::de::DeserializeOwned>() {}
+    assert_serde::<QueueState<u16>>();
+
+    for mode in [MembershipMode::Visited, MembershipMode::InQueue] {
+      let mut buf = [0u16; 4];
+      let mut membership = [0u64; 2];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        mode,
+        ProcessingOrder::Lifo,
+      );
+      for value in [3, 70, 9, 12] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+      assert_eq!(queue.pop(), Some(12));
+      assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+      let state = queue.snapshot();
+      assert_eq!(state.pending, [3, 70, 9, 1]);
+
+      let mut restored_buf = [0u16; 4];
+      let mut restored_membership = [false; 128];
+      restored_membership.insert(100);
+      let mut restored = TinySetQueue::from_snapshot(
+        &mut restored_buf,
+        &mut restored_membership,
+        &state,
+      )
+      .unwrap_or_else(|error| panic!("{error}"));
+      assert_eq!(restored.snapshot(), state);
+      assert!(!restored.contains(100));
+      assert_eq!(
+        restored.contains(12),
+        matches!(mode, MembershipMode::Visited)
+      );
+      for expected in [1, 9, 70, 3] {
+        assert_eq!(restored.pop(), Some(expected));
+      }
+
+      let mut small = [false; 64];
+      assert!(matches!(
+        TinySetQueue::from_snapshot(&mut restored_buf, &mut small, &state),
+        Err(CaptureError::MembershipTooSmall)
+      ));
+      let mut short = [0u16; 3];
+      assert!(matches!(
+        TinySetQueue::from_snapshot(&mut short, &mut small, &state),
+        Err(CaptureError::BufferTooSmall)
+      ));
+    }
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn from_snapshot_rejects_inconsistent_state() {
+    use super::QueueState;
+
+    let state = |pending: &[u16], members: &[usize], mode| QueueState {
+      pending: pending.to_vec(),
+      members: members.to_vec(),
+      mode,
+      order: ProcessingOrder::Fifo,
+    };
+    let rejected = [
+      // A value pending twice.
+      state(&[3, 3], &[3], MembershipMode::Visited),
+      state(&[3, 3], &[3], MembershipMode::InQueue),
+      // A pending value that is not a member.
+      state(&[3, 4], &[3], MembershipMode::Visited),
+      state(&[3, 4], &[3], MembershipMode::InQueue),
+      // An `InQueue` member that is not pending.
+      state(&[3], &[3, 5], MembershipMode::InQueue),
+    ];
+    for state in &rejected {
+      let mut buf = [0u16; 4];
+      let mut membership = [false; 8];
+      assert!(matches!(
+        TinySetQueue::from_snapshot(&mut buf, &mut membership, state),
+        Err(CaptureError::InconsistentState)
+      ));
+    }
+
+    // `Visited` keeps members that were already popped.
+    let mut buf = [0u16; 4];
+    let mut membership = [false; 8];
+    let visited = state(&[3], &[3, 5], MembershipMode::Visited);
+    let Ok(mut queue) =
+      TinySetQueue::from_snapshot(&mut buf, &mut membership, &visited)
+    else {
+      panic!("consistent state was rejected");
+    };
+    assert_eq!(queue.push(5), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.pop(), None);
+  }
+
+  #[test]
+  fn remaining_counts_free_slots() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.remaining(), 3);
+    let batch = [1, 2];
+    if queue.remaining() >= batch.len() {
+      assert_eq!(queue.try_push_all(&batch), 2);
+    }
+    assert_eq!(queue.remaining(), 1);
+    assert_eq!(queue.push(3), Ok(PushResult::Inserted));
+    assert_eq!(queue.remaining(), 0);
+    queue.clear();
+  }
+
+  #[cfg(all(feature = "atomic", feature = "std", target_has_atomic = "64"))]
+  #[test]
+  fn atomic_backing_allows_concurrent_reads() {
+    use super::SetBacking;
+    use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+    let words: [AtomicU64; 2] = Default::default();
+    let done = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+      let reader = scope.spawn(|| {
+        while !done.load(Ordering::Acquire) {
+          assert!(!SetBacking::contains(&words[..], 5));
+        }
+        SetBacking::contains(&words[..], 100)
+      });
+
+      let mut writer = &words[..];
+      let mut buf = [0usize; 4];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut writer,
+        MembershipMode::Visited,
+        ProcessingOrder::Fifo,
+      );
+      for value in [1, 64, 100] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+      assert_eq!(queue.push(128), Err(PushError::OutOfRange(128)));
+      assert_eq!(queue.pop(), Some(1));
+      assert_eq!(queue.push(1), Ok(PushResult::AlreadyPresent));
+      assert_eq!(queue.members_count(), 3);
+      done.store(true, Ordering::Release);
+      assert!(reader.join().unwrap());
+    });
+
+    let mut owned: [AtomicU64; 2] = Default::default();
+    let backing = &mut owned[..];
+    backing.insert(3);
+    backing.insert(70);
+    assert_eq!(backing.count(), 2);
+    backing.remove(3);
+    assert!(!SetBacking::contains(backing, 3));
+    assert!(SetBacking::contains(backing, 70));
+    backing.clear_up_to(64);
+    assert!(SetBacking::contains(backing, 70));
+    backing.clear_all();
+    assert_eq!(backing.count(), 0);
+  }
+
+  #[test]
+  fn deque_ends_interleave_across_wrap() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    // 0-1 BFS style: cheap edges go to the front, others to the back.
+    assert_eq!(queue.push_back(5), Ok(PushResult::Inserted));
+    assert_eq!(queue.push_front(3), Ok(PushResult::Inserted)); // wraps to slot 3
+    assert_eq!(queue.push_back(7), Ok(PushResult::Inserted));
+    assert_eq!(queue.push_front(7), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.push_back(3), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.push_front(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.push_back(9), Err(PushError::Full(9)));
+
+    assert_eq!(queue.pop_back(), Some(7));
+    assert_eq!(queue.pop_front(), Some(2));
+    assert!(!queue.contains(2) && !queue.contains(7));
+    assert_eq!(queue.push_back(8), Ok(PushResult::Inserted));
+    assert_eq!(queue.push_front(1), Ok(PushResult::Inserted));
+    for expected in [1, 3, 5, 8] {
+      assert_eq!(queue.pop_front(), Some(expected));
+    }
+    assert_eq!(queue.pop_front(), None);
+    assert_eq!(queue.pop_back(), None);
+  }
+
+  #[test]
+  fn clear_pending_keeps_visited_set() {
+    for mode in [MembershipMode::Visited, MembershipMode::InQueue] {
+      let mut buf = [0usize; 4];
+      let mut membership = [false; 8];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        mode,
+        ProcessingOrder::Fifo,
+      );
+      for value in [1, 2, 3] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+      assert_eq!(queue.pop(), Some(1));
+
+      queue.clear_pending();
+      assert!(queue.is_empty());
+      assert_eq!(queue.pop(), None);
+      let visited = matches!(mode, MembershipMode::Visited);
+      for value in [1, 2, 3] {
+        assert_eq!(queue.contains(value), visited);
+      }
+      assert_eq!(queue.push(4), Ok(PushResult::Inserted));
+      assert_eq!(queue.pop(), Some(4));
+    }
+  }
+
+  #[test]
+  fn push_or_replace_overwrites_pending_copy() {
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    struct Tagged {
+      key: u8,
+      generation: u8,
+    }
+
+    impl From<Tagged> for usize {
+      fn from(tagged: Tagged) -> usize {
+        usize::from(tagged.key)
+      }
+    }
+
+    let tagged = |key, generation| Tagged { key, generation };
+    let mut buf = [tagged(0, 0); 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    for key in [1, 2, 3] {
+      assert_eq!(queue.push(tagged(key, 0)), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(tagged(1, 0)));
+    assert_eq!(queue.push(tagged(4, 0)), Ok(PushResult::Inserted));
+
+    assert_eq!(
+      queue.push_or_replace(tagged(4, 1)),
+      Ok(PushResult::Replaced)
+    );
+    assert_eq!(
+      queue.push_or_replace(tagged(2, 5)),
+      Ok(PushResult::Replaced)
+    );
+    assert_eq!(
+      queue.push_or_replace(tagged(1, 9)),
+      Ok(PushResult::AlreadyPresent)
+    );
+    assert_eq!(
+      queue.push_or_replace(tagged(5, 0)),
+      Err(PushError::Full(tagged(5, 0)))
+    );
+    assert_eq!(queue.len(), 3);
+    for expected in [tagged(2, 5), tagged(3, 0), tagged(4, 1)] {
+      assert_eq!(queue.pop(), Some(expected));
+    }
+  }
+
+  #[test]
+  fn array_queue_owns_storage_inline() {
+    use super::TinySetArrayQueue;
+
+    let mut queue: TinySetArrayQueue<u16, 3, 2> =
+      TinySetArrayQueue::new(MembershipMode::Visited, ProcessingOrder::Fifo);
+    assert_eq!(queue.capacity(), 3);
+    assert_eq!(queue.membership_capacity(), 128);
+    assert!(queue.is_empty());
+
+    for value in [5, 100, 7] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert!(queue.is_full());
+    assert_eq!(queue.push(5), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.push(128), Err(PushError::OutOfRange(128)));
+    assert_eq!(queue.pop(), Some(5));
+    assert_eq!(queue.push(5), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.push(9), Ok(PushResult::Inserted)); // wraps to slot 0
+
+    let mut seen = [0u16; 3];
+    for (slot, value) in seen.iter_mut().zip(queue.iter()) {
+      *slot = *value;
+    }
+    assert_eq!(seen, [100, 7, 9]);
+
+    // The queue moves as a plain value.
+    let mut moved = queue;
+    assert_eq!(moved.len(), 3);
+    moved.clear();
+    assert!(moved.is_empty());
+    assert_eq!(moved.push(5), Ok(PushResult::Inserted));
+  }
+
+  #[test]
+  fn visited_indices_lists_marked_indices_in_order() {
+    use super::SetBacking;
+
+    let mut buf = [0usize; 4];
+    let mut membership = [0u64; 3];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Lifo,
+    );
+    assert_eq!(queue.visited_indices().next(), None);
+    for value in [130, 0, 64, 63] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    while queue.pop().is_some() {}
+
+    let mut seen = [0usize; 5];
+    let mut count = 0;
+    for (slot, index) in seen.iter_mut().zip(queue.visited_indices()) {
+      *slot = index;
+      count += 1;
+    }
+    assert_eq!((count, seen), (4, [0, 63, 64, 130, 0]));
+
+    let mut flags = [false; 70];
+    let mut words32 = [0u32; 3];
+    for index in [2, 33, 69] {
+      flags.insert(index);
+      words32.insert(index);
+    }
+    let mut bits = flags.iter_set_bits();
+    assert_eq!(bits.next(), Some(2));
+    assert_eq!(bits.next(), Some(33));
+    assert_eq!(bits.next(), Some(69));
+    assert_eq!(bits.next(), None);
+    assert_eq!(bits.next(), None);
+    assert!(words32.iter_set_bits().eq([2, 33, 69]));
+  }
+
+  #[test]
+  fn validate_capacity_reports_backing_size() {
+    let mut buf = [0u8; 2];
+    let mut membership = [0u64; 2];
+    let queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.validate_capacity(0), Ok(()));
+    assert_eq!(queue.validate_capacity(127), Ok(()));
+    assert_eq!(queue.validate_capacity(128), Err(128));
+    assert_eq!(queue.validate_capacity(usize::MAX), Err(128));
+  }
+
+  #[test]
+  fn for_loop_over_mut_queue_drains_it() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
     );
-    #[cfg(feature = "clear_on_new")]
-    in_queue.clear_all();
-    let mask = buf.len() - 1;
-    TinySetQueuePow2 {
-      buf,
-      in_queue,
-      mode,
-      order,
-      mask,
-      head: 0,
-      tail: 0,
-      len: 0,
+    for value in [3, 1, 2] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!((&mut queue).into_iter().len(), 3);
+    assert!(queue.is_empty());
+
+    for value in [4, 5, 6] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    let mut last = 0;
+    for value in &mut queue {
+      assert!(value > last);
+      last = value;
+      if value == 5 {
+        break;
+      }
     }
+    assert_eq!(last, 5);
+    assert!(queue.is_empty());
+    assert!(queue.contains(6));
   }
 
-  /// Clears the queue without freeing any backing storage.
-  pub fn clear(&mut self) {
-    self.in_queue.clear_all();
-    self.head = 0;
-    self.tail = 0;
-    self.len = 0;
+  #[test]
+  fn discard_next_matches_pop_semantics() {
+    for mode in [MembershipMode::InQueue, MembershipMode::Visited] {
+      let mut buf = [0u8; 2];
+      let mut membership = [false; 4];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        mode,
+        ProcessingOrder::Lifo,
+      );
+      assert_eq!(queue.discard_next(), None);
+      assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+      assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+      assert_eq!(queue.discard_next(), Some(2));
+      assert_eq!(queue.len(), 1);
+      assert_eq!(queue.contains(2), mode == MembershipMode::Visited);
+      assert_eq!(queue.pop(), Some(1));
+    }
   }
 
-  #[inline]
-  pub fn capacity(&self) -> usize {
-    self.buf.len()
+  #[test]
+  fn mark_unvisited_reopens_popped_values_only() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    for value in [1, 2] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(1));
+
+    assert!(!queue.mark_unvisited(2)); // still pending
+    assert!(!queue.mark_unvisited(3)); // never visited
+    assert!(!queue.mark_unvisited(9)); // out of range
+    assert!(queue.mark_unvisited(1));
+    assert!(!queue.contains(1));
+    assert!(!queue.mark_unvisited(1));
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(2), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.len(), 2);
+    queue.clear();
+
+    let (buf, membership) = queue.into_parts();
+    let mut queue = TinySetQueue::new(
+      buf,
+      membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.push(4), Ok(PushResult::Inserted));
+    assert!(!queue.mark_unvisited(4));
+    assert!(queue.contains(4));
+    queue.clear();
   }
 
-  #[inline]
-  pub fn len(&self) -> usize {
-    self.len
+  #[test]
+  fn clear_range_leaves_outside_indices_alone() {
+    use super::SetBacking;
+
+    fn check<S: SetBacking + ?Sized>(backing: &mut S) {
+      backing.clear_all();
+      for index in 0..backing.capacity() {
+        backing.insert(index);
+      }
+      let cases = [(3, 5), (60, 70), (0, 64), (100, 300), (9, 9), (7, 2)];
+      for (start, end) in cases {
+        backing.clear_range(start, end);
+      }
+      for index in 0..backing.capacity() {
+        let cleared = cases
+          .iter()
+          .any(|&(start, end)| (start..end).contains(&index));
+        assert_eq!(backing.contains(index), !cleared, "index {index}");
+      }
+    }
+
+    check(&mut [false; 150]);
+    check(&mut [false; 150][..]);
+    check(&mut [0u64; 3]);
+    check(&mut [0u64; 3][..]);
+    check(&mut [0u32; 5][..]);
+    check(&mut [0u128; 2]);
+    check(&mut [0u8; 19]);
+    check(&mut [0u8; 19][..]);
   }
 
-  #[inline]
-  pub fn is_empty(&self) -> bool {
-    self.len == 0
+  #[test]
+  fn clear_touched_resets_high_water_bound() {
+    use super::QueueConfig;
+
+    let mut buf = [0usize; 4];
+    let mut membership = [0u64; 16];
+    let mut queue = TinySetQueue::new_with_config(
+      &mut buf,
+      &mut membership,
+      QueueConfig {
+        clear_on_new: true,
+        ..QueueConfig::bfs()
+      },
+    );
+    for value in [3, 70, 12] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.high_water(), 71);
+
+    queue.clear_touched();
+    assert!(queue.is_empty());
+    assert_eq!(queue.high_water(), 0);
+    assert_eq!(queue.members_count(), 0);
+    assert_eq!(queue.push(3), Ok(PushResult::Inserted));
+    assert_eq!(queue.high_water(), 4);
   }
 
-  #[inline]
-  pub fn is_full(&self) -> bool {
-    self.len == self.buf.len()
+  #[test]
+  fn push_unchecked_still_dedups() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.push_unchecked(3), PushResult::Inserted);
+    assert_eq!(queue.push_unchecked(3), PushResult::AlreadyPresent);
+    assert_eq!(queue.push_unchecked(1), PushResult::Inserted);
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.push_unchecked(2), PushResult::Inserted); // wraps
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), Some(2));
   }
 
-  pub fn push(&mut self, value: T) -> Result<PushResult, T> {
-    let idx: usize = value.into();
+  #[test]
+  #[should_panic]
+  fn push_unchecked_panics_out_of_range() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 4];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    queue.push_unchecked(4);
+  }
 
-    if idx >= self.in_queue.capacity() {
-      return Err(value);
+  #[test]
+  fn front_and_back_ignore_processing_order() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Lifo,
+    );
+    assert_eq!((queue.front(), queue.back()), (None, None));
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
     }
+    assert_eq!(queue.pop_front(), Some(1));
+    assert_eq!(queue.push(4), Ok(PushResult::Inserted)); // wraps to slot 0
+    assert_eq!(queue.front(), Some(&2));
+    assert_eq!(queue.back(), Some(&4));
+    assert_eq!(queue.peek(), Some(&4));
+    queue.clear();
+  }
 
-    if self.in_queue.contains(idx) {
-      return Ok(PushResult::AlreadyPresent);
+  #[test]
+  fn builder_defaults_to_in_queue_fifo() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::builder(&mut buf, &mut membership).build();
+    for value in [1, 2] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
     }
+    assert_eq!(queue.pop(), Some(1));
+    assert!(!queue.contains(1));
+    queue.clear();
 
-    if self.is_full() {
-      return Err(value);
+    let (buf, membership) = queue.into_parts();
+    let mut queue = TinySetQueue::builder(buf, membership)
+      .order(ProcessingOrder::Lifo)
+      .mode(MembershipMode::Visited)
+      .build();
+    for value in [1, 2] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
     }
-
-    self.buf[self.tail] = value;
-    self.in_queue.insert(idx);
-
-    self.tail = (self.tail + 1) & self.mask;
-    self.len += 1;
-
-    Ok(PushResult::Inserted)
+    assert_eq!(queue.pop(), Some(2));
+    assert!(queue.contains(2));
   }
 
-  pub fn pop(&mut self) -> Option<T> {
-    if self.is_empty() {
-      return None;
+  #[cfg(feature = "alloc")]
+  #[test]
+  fn keyed_queue_moves_non_copy_values() {
+    use super::KeyedTinySetQueue;
+    use alloc::string::{String, ToString};
+
+    #[derive(Debug, PartialEq)]
+    struct Task {
+      id: usize,
+      label: String,
     }
+    let task = |id: usize| Task {
+      id,
+      label: id.to_string(),
+    };
 
-    let index = match self.order {
-      ProcessingOrder::Fifo => {
-        let idx = self.head;
-        self.head = (self.head + 1) & self.mask;
-        idx
-      }
-      ProcessingOrder::Lifo => {
-        let idx = (self.tail.wrapping_sub(1)) & self.mask;
-        self.tail = idx;
-        idx
+    for order in [
+      ProcessingOrder::Fifo,
+      ProcessingOrder::Lifo,
+      ProcessingOrder::InsertionMin,
+    ] {
+      let mut buf: [Option<Task>; 3] = Default::default();
+      let mut membership = [0u64; 1];
+      let mut queue = KeyedTinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        |task: &Task| task.id,
+        MembershipMode::InQueue,
+        order,
+      );
+      for id in [5, 2, 9] {
+        assert_eq!(queue.push(task(id)), Ok(PushResult::Inserted));
       }
-    };
+      assert_eq!(queue.push(task(2)), Ok(PushResult::AlreadyPresent));
+      assert_eq!(queue.push(task(1)), Err(PushError::Full(task(1))));
+      assert_eq!(queue.push(task(64)), Err(PushError::OutOfRange(task(64))));
 
-    let value = self.buf[index];
-    let idx: usize = value.into();
-    if matches!(self.mode, MembershipMode::InQueue) {
-      self.in_queue.remove(idx);
+      let first = queue.pop().unwrap();
+      assert!(!queue.contains(&first));
+      // The freed slot is reused across the wrap.
+      assert_eq!(queue.push(task(7)), Ok(PushResult::Inserted));
+      let mut labels = [0usize; 4];
+      labels[0] = first.id;
+      for slot in labels[1..].iter_mut() {
+        let next = queue.peek().map(|task| task.id);
+        let popped = queue.pop().unwrap();
+        assert_eq!(Some(popped.id), next);
+        assert_eq!(popped.label, popped.id.to_string());
+        *slot = popped.id;
+      }
+      assert!(queue.is_empty());
+      match order {
+        ProcessingOrder::Fifo => assert_eq!(labels, [5, 2, 9, 7]),
+        ProcessingOrder::Lifo => assert_eq!(labels, [9, 7, 2, 5]),
+        _ => assert_eq!(labels, [2, 5, 7, 9]),
+      }
     }
+  }
 
-    self.len -= 1;
+  #[test]
+  fn as_slices_splits_at_wrap() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.as_slices(), (&[][..], &[][..]));
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.as_slices(), (&[1, 2, 3][..], &[][..]));
 
-    Some(value)
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), Some(2));
+    for value in [4, 5, 6] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.as_slices(), (&[3, 4][..], &[5, 6][..]));
+    queue.clear();
   }
-}
-
-#[cfg(test)]
-mod tests {
-  use super::{MembershipMode, ProcessingOrder, PushResult, TinySetQueue};
 
   #[test]
-  fn basic_push_pop_in_queue() {
-    let mut buf = [0u8; 4];
+  fn get_indexes_from_the_pop_side_across_wrap() {
+    let mut buf = [0usize; 4];
     let mut membership = [false; 8];
     let mut queue = TinySetQueue::new(
       &mut buf,
@@ -496,24 +8679,61 @@ mod tests {
       MembershipMode::InQueue,
       ProcessingOrder::Fifo,
     );
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    queue.pop();
+    queue.pop();
+    for value in [4, 5, 6] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
 
-    assert!(queue.is_empty());
-    assert_eq!(queue.capacity(), 4);
-
-    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
-    assert_eq!(queue.push(1), Ok(PushResult::AlreadyPresent));
-    assert_eq!(queue.len(), 1);
+    // The ring now wraps: 3 sits in the last slot, 4..=6 at the start.
+    for (order, expected) in [
+      (ProcessingOrder::Fifo, [3, 4, 5, 6]),
+      (ProcessingOrder::Lifo, [6, 5, 4, 3]),
+    ] {
+      queue.set_order(order);
+      for (logical, value) in expected.iter().enumerate() {
+        assert_eq!(queue.get(logical), Some(value));
+        assert_eq!(queue.get(logical), queue.iter().nth(logical));
+      }
+      assert_eq!(queue.get(4), None);
+    }
+  }
 
+  #[test]
+  fn reset_to_reconfigures_and_wipes_visited_set() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Fifo,
+    );
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
     assert_eq!(queue.pop(), Some(1));
-    assert_eq!(queue.len(), 0);
 
-    // Membership cleared in InQueue mode -> can be inserted again.
-    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    queue.reset_to(MembershipMode::InQueue, ProcessingOrder::Lifo);
+    assert!(queue.is_empty());
+    assert_eq!(queue.mode, MembershipMode::InQueue);
+    assert_eq!(queue.order, ProcessingOrder::Lifo);
+    assert!(!queue.contains(1) && !queue.contains(2));
+
+    for value in [1, 2] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(2));
+    assert!(!queue.contains(2));
+    queue.clear();
   }
 
   #[test]
-  fn visited_mode_prevents_requeue() {
-    let mut buf = [0u8; 4];
+  fn set_mode_changes_only_later_pops() {
+    let mut buf = [0usize; 4];
     let mut membership = [false; 8];
     let mut queue = TinySetQueue::new(
       &mut buf,
@@ -521,236 +8741,371 @@ mod tests {
       MembershipMode::Visited,
       ProcessingOrder::Fifo,
     );
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(1));
 
-    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    queue.set_mode(MembershipMode::InQueue);
     assert_eq!(queue.pop(), Some(2));
-    assert_eq!(queue.push(2), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(1), Ok(PushResult::AlreadyPresent));
+    queue.clear();
+  }
+
+  #[test]
+  fn wide_key_rejects_unconvertible_keys_as_out_of_range() {
+    let mut buf = [WideKey(0u128); 4];
+    let mut membership = [0u64; 1];
+    let mut queue = TinySetQueue::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    let huge = WideKey(u128::MAX);
+    assert_eq!(queue.push(huge), Err(PushError::OutOfRange(huge)));
+    assert_eq!(
+      queue.push(WideKey(64)),
+      Err(PushError::OutOfRange(WideKey(64)))
+    );
+    assert_eq!(queue.push(WideKey(7)), Ok(PushResult::Inserted));
+    assert!(queue.contains(WideKey(7)));
+    assert_eq!(queue.pop(), Some(WideKey(7)));
+    assert!(queue.is_empty());
+  }
+
+  #[test]
+  fn is_pending_distinguishes_popped_visited_values() {
+    for mode in [MembershipMode::InQueue, MembershipMode::Visited] {
+      let mut buf = [0usize; 4];
+      let mut membership = [false; 8];
+      let mut queue = TinySetQueue::new(
+        &mut buf,
+        &mut membership,
+        mode,
+        ProcessingOrder::Fifo,
+      );
+      for value in [1, 2] {
+        assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+      }
+      assert_eq!(queue.pop(), Some(1));
+
+      assert!(queue.is_pending(2) && queue.is_visited(2));
+      assert!(!queue.is_pending(1));
+      assert_eq!(queue.is_visited(1), mode == MembershipMode::Visited);
+      assert!(!queue.is_pending(3) && !queue.is_visited(3));
+      assert!(!queue.is_pending(9));
+      queue.clear();
+    }
+  }
+}
+
+#[cfg(all(test, feature = "pow2", feature = "std"))]
+mod pow2_tests {
+  use super::{
+    MembershipMode, ProcessingOrder, PushError, PushResult, QueueView,
+    TinySetQueuePow2,
+  };
+
+  #[test]
+  fn rejects_non_power_of_two() {
+    let mut buf = [0u8; 3];
+    let mut membership = [false; 8];
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+      TinySetQueuePow2::new(
+        &mut buf,
+        &mut membership,
+        MembershipMode::InQueue,
+        ProcessingOrder::Fifo,
+      );
+    }));
+    assert!(result.is_err());
   }
 
   #[test]
-  fn lifo_order_pops_most_recent() {
+  fn push_pop_wraparound_uses_mask() {
     let mut buf = [0u8; 4];
     let mut membership = [false; 8];
-    let mut queue = TinySetQueue::new(
+    let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
       MembershipMode::InQueue,
-      ProcessingOrder::Lifo,
+      ProcessingOrder::Fifo,
     );
 
+    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
     assert_eq!(queue.push(1), Ok(PushResult::Inserted));
     assert_eq!(queue.push(2), Ok(PushResult::Inserted));
     assert_eq!(queue.push(3), Ok(PushResult::Inserted));
-    assert_eq!(queue.len(), 3);
+    assert!(queue.is_full());
 
-    assert_eq!(queue.pop(), Some(3));
-    assert_eq!(queue.pop(), Some(2));
+    assert_eq!(queue.pop(), Some(0));
+    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
     assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), Some(2));
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.pop(), Some(0));
     assert!(queue.is_empty());
-    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
   }
 
   #[test]
-  fn clear_resets_membership_and_indices() {
-    let mut buf = [0u8; 2];
-    let mut membership = [false; 4];
-    let mut queue = TinySetQueue::new(
+  fn pow2_lifo_order_uses_tail() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 32];
+    let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
       MembershipMode::InQueue,
-      ProcessingOrder::Fifo,
+      ProcessingOrder::Lifo,
     );
 
-    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
-    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
-    assert!(queue.is_full());
+    assert_eq!(queue.push(10), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(11), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(12), Ok(PushResult::Inserted));
 
-    queue.clear();
+    assert_eq!(queue.pop(), Some(12));
+    assert_eq!(queue.pop(), Some(11));
+    assert_eq!(queue.pop(), Some(10));
     assert!(queue.is_empty());
-    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
-    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(10), Ok(PushResult::Inserted));
   }
 
-  #[cfg(feature = "clear_on_new")]
   #[test]
-  fn new_clears_membership_bitmap() {
-    let mut buf = [0u8; 2];
-    let mut membership = [true; 4];
-    let mut queue = TinySetQueue::new(
+  fn pow2_supports_bitset_backing() {
+    let mut buf = [0u8; 4];
+    let mut membership = [0u64; 1];
+    let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
       MembershipMode::InQueue,
       ProcessingOrder::Fifo,
     );
 
-    assert!(queue.is_empty());
-    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(17), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
   }
-
-  #[cfg(not(feature = "clear_on_new"))]
   #[test]
-  fn new_preserves_membership_bitmap() {
+  fn pow2_iter_indexed_matches_keys_across_wraparound() {
     let mut buf = [0u8; 2];
-    let mut membership = [true; 4];
-    let mut queue = TinySetQueue::new(
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
       MembershipMode::InQueue,
       ProcessingOrder::Fifo,
     );
 
-    assert!(queue.is_empty());
-    assert_eq!(queue.push(0), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.push(3), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(3));
+    assert_eq!(queue.push(4), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted)); // wraps to slot 0
+
+    let pairs: Vec<(usize, u8)> = queue
+      .iter_indexed()
+      .map(|(key, value)| (key, *value))
+      .collect();
+    assert_eq!(pairs, vec![(4, 4), (5, 5)]);
   }
 
   #[test]
-  fn push_rejects_out_of_range_index() {
-    let mut buf = [0u8; 2];
-    let mut membership = [false; 2];
-    let mut queue = TinySetQueue::new(
+  fn pow2_queue_view_peeks_next_value() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
       MembershipMode::InQueue,
       ProcessingOrder::Fifo,
     );
 
-    assert_eq!(queue.push(3), Err(3));
-    assert!(queue.is_empty());
+    assert_eq!(queue.push(6), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert_eq!(QueueView::peek(&queue), Some(&6));
+    assert!(QueueView::contains(&queue, 2));
+    assert!(!QueueView::is_full(&queue));
   }
 
   #[test]
-  fn push_rejects_when_full() {
+  fn grow_pow2_recomputes_mask_and_preserves_order() {
     let mut buf = [0u8; 2];
-    let mut membership = [false; 4];
-    let mut queue = TinySetQueue::new(
+    let mut membership = [false; 16];
+    let mut bigger_buf = [0u8; 8];
+    let mut bigger_membership = [true; 16];
+    let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
       MembershipMode::InQueue,
       ProcessingOrder::Fifo,
     );
 
-    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
     assert_eq!(queue.push(1), Ok(PushResult::Inserted));
-    assert!(queue.is_full());
-    assert_eq!(queue.push(2), Err(2));
-    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(3), Ok(PushResult::Inserted)); // wraps to slot 0
+    assert_eq!(queue.push(4), Err(PushError::Full(4)));
+
+    let mut odd_buf = [0u8; 6];
+    let mut odd_membership = [false; 16];
+    let Err(queue) = queue.grow_pow2(&mut odd_buf, &mut odd_membership) else {
+      panic!("non-power-of-two buffer must be rejected");
+    };
+    let Ok(mut grown) =
+      queue.grow_pow2(&mut bigger_buf, &mut bigger_membership)
+    else {
+      panic!("larger power-of-two buffer must be accepted");
+    };
+    assert_eq!(grown.capacity(), 8);
+    assert_eq!(grown.push(3), Ok(PushResult::AlreadyPresent));
+    assert_eq!(grown.push(1), Ok(PushResult::Inserted));
+    for value in [5, 6, 7, 8, 9] {
+      assert_eq!(grown.push(value), Ok(PushResult::Inserted));
+    }
+    assert!(grown.is_full());
+    let drained: Vec<u8> = core::iter::from_fn(|| grown.pop()).collect();
+    assert_eq!(drained, vec![2, 3, 1, 5, 6, 7, 8, 9]);
   }
 
   #[test]
-  fn ring_buffer_wraparound_preserves_membership() {
-    let mut buf = [0u8; 3];
-    let mut membership = [false; 6];
-    let mut queue = TinySetQueue::new(
+  fn pow2_insertion_min_pops_smallest_key_first() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
       MembershipMode::InQueue,
-      ProcessingOrder::Fifo,
+      ProcessingOrder::InsertionMin,
     );
-
+    for value in [8, 2, 11] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(2));
     assert_eq!(queue.push(0), Ok(PushResult::Inserted));
-    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
-    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
-    assert!(queue.is_full());
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+    for expected in [0, 5, 8, 11] {
+      assert_eq!(queue.pop(), Some(expected));
+    }
+    assert_eq!(queue.pop(), None);
+  }
 
-    assert_eq!(queue.pop(), Some(0));
-    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
-    assert_eq!(queue.pop(), Some(1));
-    assert_eq!(queue.pop(), Some(2));
-    assert_eq!(queue.pop(), Some(0));
-    assert!(queue.is_empty());
+  #[test]
+  fn pow2_peek_matches_next_pop() {
+    for order in [ProcessingOrder::Fifo, ProcessingOrder::Lifo] {
+      let mut buf = [0usize; 2];
+      let mut membership = [false; 8];
+      let mut queue = TinySetQueuePow2::new(
+        &mut buf,
+        &mut membership,
+        MembershipMode::InQueue,
+        order,
+      );
+      assert_eq!(TinySetQueuePow2::peek(&queue), None);
+      assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+      assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+      queue.pop();
+      assert_eq!(queue.push(3), Ok(PushResult::Inserted));
+      while let Some(&next) = TinySetQueuePow2::peek(&queue) {
+        assert_eq!(queue.pop(), Some(next));
+      }
+    }
   }
 
   #[test]
-  fn zero_capacity_queue_behaves_consistently() {
-    let mut buf: [u8; 0] = [];
-    let mut membership = [false; 1];
-    let mut queue = TinySetQueue::new(
+  fn pow2_contains_checks_bounds() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 4];
+    let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
       MembershipMode::InQueue,
       ProcessingOrder::Fifo,
     );
-
-    assert_eq!(queue.capacity(), 0);
-    assert!(queue.is_empty());
-    assert!(queue.is_full());
-    assert_eq!(queue.push(0), Err(0));
-    assert_eq!(queue.pop(), None);
+    assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert!(queue.contains(2));
+    assert!(!queue.contains(9));
+    assert_eq!(queue.pop(), Some(2));
+    assert!(!queue.contains(2));
   }
 
   #[test]
-  fn bitset_backing_handles_high_indices() {
-    let mut buf = [0u16; 4];
-    let mut membership = [0u64; 2]; // capacity 128
-    let mut queue = TinySetQueue::new(
+  fn pow2_remaining_counts_free_slots() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 4];
+    let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
       MembershipMode::InQueue,
       ProcessingOrder::Fifo,
     );
-
-    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
-    assert_eq!(queue.push(63), Ok(PushResult::Inserted));
-    assert_eq!(queue.push(63), Ok(PushResult::AlreadyPresent));
-    assert_eq!(queue.push(64), Ok(PushResult::Inserted));
-    assert_eq!(queue.pop(), Some(0));
-    assert_eq!(queue.push(0), Ok(PushResult::Inserted)); // membership cleared after pop
+    assert_eq!(queue.remaining(), 2);
+    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.remaining(), 1);
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.remaining(), 2);
   }
 
   #[test]
-  fn bitset_backing_enforces_capacity() {
-    let mut buf = [0u8; 2];
-    let mut membership = [0u64; 1]; // capacity 64
-    let mut queue = TinySetQueue::new(
+  fn pow2_retain_compacts_wrapped_ring() {
+    let mut buf = [0usize; 4];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
-      MembershipMode::InQueue,
+      MembershipMode::Visited,
       ProcessingOrder::Fifo,
     );
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), Some(2));
+    for value in [4, 5, 6] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
 
-    assert_eq!(queue.push(63), Ok(PushResult::Inserted));
-    assert_eq!(queue.push(64), Err(64)); // out of range
+    queue.retain(|&v| v % 2 == 0);
+    assert_eq!(queue.len(), 2);
+    assert!(!queue.contains(3) && !queue.contains(5));
+    assert!(queue.contains(1));
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+    for expected in [4, 6, 5] {
+      assert_eq!(queue.pop(), Some(expected));
+    }
   }
 
   #[test]
-  fn bitset_visited_mode_persists_membership() {
-    let mut buf = [0u8; 2];
-    let mut membership = [0u64; 1];
-    let mut queue = TinySetQueue::new(
+  fn pow2_deque_ends_interleave_across_wrap() {
+    let mut buf = [0u8; 4];
+    let mut membership = [false; 16];
+    let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
       MembershipMode::Visited,
-      ProcessingOrder::Fifo,
+      ProcessingOrder::Lifo,
     );
+    assert_eq!(queue.push_back(5), Ok(PushResult::Inserted));
+    assert_eq!(queue.push_front(3), Ok(PushResult::Inserted));
+    assert_eq!(queue.push_front(5), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.push_back(7), Ok(PushResult::Inserted));
+    assert_eq!(queue.push_front(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.push_front(9), Err(PushError::Full(9)));
 
-    assert_eq!(queue.push(10), Ok(PushResult::Inserted));
-    assert_eq!(queue.pop(), Some(10));
-    assert_eq!(queue.push(10), Ok(PushResult::AlreadyPresent));
-  }
-}
-
-#[cfg(all(test, feature = "pow2", feature = "std"))]
-mod pow2_tests {
-  use super::{MembershipMode, ProcessingOrder, PushResult, TinySetQueuePow2};
-
-  #[test]
-  fn rejects_non_power_of_two() {
-    let mut buf = [0u8; 3];
-    let mut membership = [false; 8];
-    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-      TinySetQueuePow2::new(
-        &mut buf,
-        &mut membership,
-        MembershipMode::InQueue,
-        ProcessingOrder::Fifo,
-      );
-    }));
-    assert!(result.is_err());
+    assert_eq!(queue.pop_front(), Some(2));
+    assert_eq!(queue.pop_back(), Some(7));
+    assert_eq!(queue.push_front(2), Ok(PushResult::AlreadyPresent));
+    assert_eq!(queue.push_front(1), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(5));
+    for expected in [1, 3] {
+      assert_eq!(queue.pop_front(), Some(expected));
+    }
+    assert!(queue.is_empty());
   }
 
   #[test]
-  fn push_pop_wraparound_uses_mask() {
-    let mut buf = [0u8; 4];
+  fn pow2_front_and_back_wrap() {
+    let mut buf = [0u8; 2];
     let mut membership = [false; 8];
     let mut queue = TinySetQueuePow2::new(
       &mut buf,
@@ -758,58 +9113,83 @@ mod pow2_tests {
       MembershipMode::InQueue,
       ProcessingOrder::Fifo,
     );
-
-    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
+    assert_eq!(queue.back(), None);
     assert_eq!(queue.push(1), Ok(PushResult::Inserted));
     assert_eq!(queue.push(2), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(1));
     assert_eq!(queue.push(3), Ok(PushResult::Inserted));
-    assert!(queue.is_full());
+    assert_eq!((queue.front(), queue.back()), (Some(&2), Some(&3)));
+  }
 
-    assert_eq!(queue.pop(), Some(0));
-    assert_eq!(queue.push(0), Ok(PushResult::Inserted));
-    assert_eq!(queue.pop(), Some(1));
-    assert_eq!(queue.pop(), Some(2));
-    assert_eq!(queue.pop(), Some(3));
-    assert_eq!(queue.pop(), Some(0));
+  #[test]
+  fn pow2_membership_capacity_is_distinct_from_capacity() {
+    let mut buf = [0u8; 2];
+    let mut membership = [0u64; 2];
+    let mut queue = TinySetQueuePow2::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::InQueue,
+      ProcessingOrder::Fifo,
+    );
+    assert_eq!(queue.capacity(), 2);
+    assert_eq!(queue.membership_capacity(), 128);
+    assert_eq!(queue.push(128), Err(PushError::OutOfRange(128)));
     assert!(queue.is_empty());
   }
 
   #[test]
-  fn pow2_lifo_order_uses_tail() {
+  fn pow2_get_indexes_from_the_pop_side_across_wrap() {
     let mut buf = [0u8; 4];
-    let mut membership = [false; 32];
+    let mut membership = [false; 16];
     let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
       MembershipMode::InQueue,
-      ProcessingOrder::Lifo,
+      ProcessingOrder::Fifo,
     );
-
-    assert_eq!(queue.push(10), Ok(PushResult::Inserted));
-    assert_eq!(queue.push(11), Ok(PushResult::Inserted));
-    assert_eq!(queue.push(12), Ok(PushResult::Inserted));
-
-    assert_eq!(queue.pop(), Some(12));
-    assert_eq!(queue.pop(), Some(11));
-    assert_eq!(queue.pop(), Some(10));
-    assert!(queue.is_empty());
-    assert_eq!(queue.push(10), Ok(PushResult::Inserted));
+    for value in [1, 2, 3] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    queue.pop();
+    queue.pop();
+    for value in [4, 5, 6] {
+      assert_eq!(queue.push(value), Ok(PushResult::Inserted));
+    }
+    assert_eq!(queue.get(0), Some(&3));
+    assert_eq!(queue.get(3), Some(&6));
+    assert_eq!(queue.get(4), None);
   }
 
   #[test]
-  fn pow2_supports_bitset_backing() {
+  fn pow2_set_mode_switches_to_in_queue() {
     let mut buf = [0u8; 4];
-    let mut membership = [0u64; 1];
+    let mut membership = [false; 8];
     let mut queue = TinySetQueuePow2::new(
       &mut buf,
       &mut membership,
-      MembershipMode::InQueue,
+      MembershipMode::Visited,
       ProcessingOrder::Fifo,
     );
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+    queue.set_mode(MembershipMode::InQueue);
+    assert_eq!(queue.pop(), Some(5));
+    assert_eq!(queue.push(5), Ok(PushResult::Inserted));
+  }
 
-    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
-    assert_eq!(queue.push(17), Ok(PushResult::Inserted));
-    assert_eq!(queue.pop(), Some(1));
-    assert_eq!(queue.push(1), Ok(PushResult::Inserted));
+  #[test]
+  fn pow2_is_pending_scans_in_visited_mode() {
+    let mut buf = [0u8; 2];
+    let mut membership = [false; 8];
+    let mut queue = TinySetQueuePow2::new(
+      &mut buf,
+      &mut membership,
+      MembershipMode::Visited,
+      ProcessingOrder::Lifo,
+    );
+    assert_eq!(queue.push(3), Ok(PushResult::Inserted));
+    assert_eq!(queue.push(4), Ok(PushResult::Inserted));
+    assert_eq!(queue.pop(), Some(4));
+    assert!(queue.is_visited(4) && !queue.is_pending(4));
+    assert!(queue.is_pending(3));
   }
 }